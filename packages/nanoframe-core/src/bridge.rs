@@ -0,0 +1,435 @@
+//! Page side of the core: scripts injected into every webview, handling of the
+//! `{ "__nanoframe": kind, ... }` messages they post back (never forwarded to Node as
+//! `webview.ipc`), page calls into the RPC API gated by per-window capabilities, and delivery
+//! of native file drops.
+
+use crate::rpc::{RpcId, RpcRequest, RpcResponse};
+use crate::state::{App, Internal};
+use anyhow::{anyhow, Result};
+use crossbeam_channel::Sender;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::Arc;
+use tao::window::ResizeDirection;
+use wry::http::header::{HeaderValue, CONTENT_TYPE};
+use wry::http::{Request, Response, StatusCode, Uri};
+use wry::{DragDropEvent, WebViewBuilder};
+
+/// Per-window page settings shared with the webview's callbacks, changeable at runtime.
+pub struct PageSettings {
+    /// Intercept OS file drops (and emit `webview.drag*`/`webview.drop`); off leaves them to the page
+    pub drag_drop: Cell<bool>,
+    /// Also dispatch `nanoframe-dragenter`/`-dragover`/`-drop`/`-dragleave` DOM events with real paths
+    pub drop_to_page: Cell<bool>,
+    /// Origins allowed to message Node over `window.ipc`; `None` allows any
+    pub ipc_origins: RefCell<Option<Vec<String>>>,
+    /// RPC methods the page may call with `nanoframe.invoke` (exact, `clipboard.*` or `*`)
+    pub capabilities: Vec<String>,
+}
+
+impl PageSettings {
+    pub fn origin_allowed(&self, origin: &str) -> bool {
+        self.ipc_origins.borrow().as_deref().is_none_or(|allowed| origin_allowed(allowed, origin))
+    }
+
+    /// Whether the page may call `method`; entries are exact names, `prefix.*` or `*`.
+    pub fn permits(&self, method: &str) -> bool {
+        self.capabilities.iter().any(|c| {
+            c == "*" || c == method || c.strip_suffix('*').is_some_and(|prefix| prefix.ends_with('.') && method.starts_with(prefix))
+        })
+    }
+}
+
+// Drag regions follow the Electron convention: `data-nanoframe-drag-region` (or CSS
+// `app-region: drag`) marks a handle, `data-nanoframe-no-drag` / `app-region: no-drag` opts
+// children such as buttons back out. Double-clicking a drag region toggles maximize. When
+// __RESIZE_BORDER__ is non-zero, presses that close to the viewport edge start a resize.
+const FRAMELESS_SCRIPT: &str = r#"(function () {
+  if (window.__nanoframeFrameless) return;
+  window.__nanoframeFrameless = true;
+  var border = __RESIZE_BORDER__;
+  var cursors = { north: 'n-resize', south: 's-resize', east: 'e-resize', west: 'w-resize', northEast: 'ne-resize', northWest: 'nw-resize', southEast: 'se-resize', southWest: 'sw-resize' };
+  var cursorSet = false;
+  function send(msg) { if (window.ipc && window.ipc.postMessage) window.ipc.postMessage(JSON.stringify(msg)); }
+  function region(el) {
+    for (; el && el.nodeType === 1; el = el.parentElement) {
+      if (el.hasAttribute('data-nanoframe-no-drag')) return 'no-drag';
+      if (el.hasAttribute('data-nanoframe-drag-region')) return 'drag';
+      var cs = getComputedStyle(el);
+      var r = (cs.getPropertyValue('app-region') || cs.getPropertyValue('-webkit-app-region')).trim();
+      if (r === 'drag' || r === 'no-drag') return r;
+    }
+    return null;
+  }
+  function edge(x, y) {
+    if (!border) return null;
+    var w = window.innerWidth, h = window.innerHeight;
+    var ns = y < border ? 'north' : (y >= h - border ? 'south' : '');
+    var ew = x < border ? 'west' : (x >= w - border ? 'east' : '');
+    if (ns && ew) return ns + ew.charAt(0).toUpperCase() + ew.slice(1);
+    return ns || ew || null;
+  }
+  document.addEventListener('mousemove', function (e) {
+    var ed = edge(e.clientX, e.clientY);
+    if (ed) { document.documentElement.style.cursor = cursors[ed]; cursorSet = true; }
+    else if (cursorSet) { document.documentElement.style.cursor = ''; cursorSet = false; }
+  }, true);
+  document.addEventListener('mousedown', function (e) {
+    if (e.button !== 0) return;
+    var ed = edge(e.clientX, e.clientY);
+    if (ed) { e.preventDefault(); send({ __nanoframe: 'resize', edge: ed }); return; }
+    if (region(e.target) !== 'drag') return;
+    e.preventDefault();
+    send({ __nanoframe: e.detail === 2 ? 'toggleMaximize' : 'drag' });
+  }, true);
+})();"#;
+
+// Injected when a window has capabilities. Calls are matched to replies by callId; the core
+// answers through __nanoframeResolve once the dispatcher has run (or refused) the method.
+const INVOKE_SCRIPT: &str = r#"(function () {
+  if (window.__nanoframeResolve) return;
+  var pending = {}, next = 0;
+  window.nanoframe = window.nanoframe || {};
+  window.nanoframe.invoke = function (method, params) {
+    return new Promise(function (resolve, reject) {
+      if (!window.ipc || !window.ipc.postMessage) { reject(new Error('IPC unavailable')); return; }
+      var callId = String(++next);
+      pending[callId] = { resolve: resolve, reject: reject };
+      window.ipc.postMessage(JSON.stringify({ __nanoframe: 'invoke', callId: callId, method: String(method), params: params === undefined ? {} : params }));
+    });
+  };
+  window.__nanoframeResolve = function (callId, ok, value) {
+    var p = pending[callId];
+    if (!p) return;
+    delete pending[callId];
+    if (ok) { p.resolve(value); return; }
+    var err = new Error(value && value.message);
+    err.code = value && value.code;
+    p.reject(err);
+  };
+})();"#;
+
+pub fn invoke_script() -> &'static str { INVOKE_SCRIPT }
+
+/// Drag-region/resize-edge script; `resize_border` is in CSS pixels, 0 disables edge resizing.
+pub fn frameless_script(resize_border: f64) -> String {
+    FRAMELESS_SCRIPT.replace("__RESIZE_BORDER__", &resize_border.max(0.0).to_string())
+}
+
+pub fn parse_edge(edge: &str) -> Option<ResizeDirection> {
+    Some(match edge {
+        "north" => ResizeDirection::North,
+        "south" => ResizeDirection::South,
+        "east" => ResizeDirection::East,
+        "west" => ResizeDirection::West,
+        "northEast" => ResizeDirection::NorthEast,
+        "northWest" => ResizeDirection::NorthWest,
+        "southEast" => ResizeDirection::SouthEast,
+        "southWest" => ResizeDirection::SouthWest,
+        _ => return None,
+    })
+}
+
+/// Origin (`scheme://host[:port]`) of the document a message came from, as reported by the
+/// webview itself rather than the page. Opaque origins (`about:`, `data:`) are `"null"`.
+pub fn origin_of(uri: &Uri) -> String {
+    match (uri.scheme_str(), uri.authority()) {
+        (Some(scheme), Some(authority)) => format!("{}://{}", scheme, authority.as_str().rsplit('@').next().unwrap_or_default()),
+        (Some("file"), None) => "file://".into(),
+        _ => "null".into(),
+    }
+}
+
+// Entries are exact origins, `*`, or `scheme://*.domain` for any subdomain of domain
+fn origin_allowed(allowed: &[String], origin: &str) -> bool {
+    allowed.iter().any(|pattern| {
+        pattern == "*" || pattern == origin || pattern.split_once("://*.").is_some_and(|(scheme, domain)| {
+            origin.strip_prefix(scheme).and_then(|rest| rest.strip_prefix("://")).is_some_and(|host| host.ends_with(&format!(".{}", domain)))
+        })
+    })
+}
+
+// Whether a message came from the top-level document. WebView2 only delivers the top-level
+// document's messages to this handler (frames have their own event), so it is known on Windows;
+// wry doesn't pass WebKit's frame info through, so it is unknown (null) on macOS and Linux.
+fn is_main_frame() -> Option<bool> {
+    if cfg!(target_os = "windows") { Some(true) } else { None }
+}
+
+/// The `window.ipc` handler for a webview. Core messages go to the event loop; everything else is
+/// forwarded to Node as `webview.ipc` with the verified origin, or dropped (`webview.ipcBlocked`)
+/// when the origin isn't on the window's allowlist. On Linux the origin is always the top-level
+/// document's, so frames it embeds pass the allowlist as that document.
+pub fn ipc_handler(window_id: String, settings: Rc<PageSettings>, tx_out: Sender<RpcResponse>, tx_internal: Sender<Internal>) -> impl Fn(Request<String>) + 'static {
+    move |request: Request<String>| {
+        let body = request.body();
+        let payload = serde_json::from_str::<Value>(body).unwrap_or(json!({ "raw": body }));
+        // The url is the sending document's (the top-level one on Linux/Windows, the frame on macOS)
+        let url = request.uri().to_string();
+        let origin = origin_of(request.uri());
+        if payload.get("__nanoframe").is_some_and(|v| v.is_string()) {
+            let _ = tx_internal.send(Internal::Bridge { window_id: window_id.clone(), payload, origin });
+            return;
+        }
+        if !settings.origin_allowed(&origin) {
+            let _ = tx_out.send(RpcResponse::notify("webview.ipcBlocked", json!({ "windowId": window_id, "origin": origin, "url": url, "isMainFrame": is_main_frame() })));
+            return;
+        }
+        let _ = tx_out.send(RpcResponse::notify("webview.ipc", json!({ "windowId": window_id, "payload": payload, "origin": origin, "url": url, "isMainFrame": is_main_frame() })));
+    }
+}
+
+/// Check a `csp` option up front, before any window is built for it.
+pub fn csp_header(csp: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(csp).map_err(|_| anyhow!("csp contains characters not allowed in a header"))
+}
+
+/// Serve `html` from a per-window custom protocol so it can carry a Content-Security-Policy
+/// header (`with_html` content has no response headers). Returns the builder and the page origin.
+pub fn serve_html<'a>(wvb: WebViewBuilder<'a>, window_id: &str, html: String, csp: HeaderValue) -> (WebViewBuilder<'a>, String) {
+    // One scheme per window: a web context (profile) can't register the same scheme twice
+    let scheme = format!("nanoframe-{}", &window_id[..8]);
+    // Windows exposes custom protocols as http://<scheme>.localhost
+    #[cfg(target_os = "windows")]
+    let origin = format!("http://{}.localhost", scheme);
+    #[cfg(not(target_os = "windows"))]
+    let origin = format!("{}://localhost", scheme);
+    let html: Arc<[u8]> = html.into_bytes().into();
+    let wvb = wvb.with_url(format!("{}/", origin)).with_custom_protocol(scheme, move |_, request| {
+        let found = request.uri().path() == "/";
+        Response::builder()
+            .status(if found { StatusCode::OK } else { StatusCode::NOT_FOUND })
+            .header(CONTENT_TYPE, "text/html; charset=utf-8")
+            .header("Content-Security-Policy", csp.clone())
+            .body(if found { Cow::Owned(html.to_vec()) } else { Cow::Borrowed(&[][..]) })
+            .unwrap_or_default()
+    });
+    (wvb, origin)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetIpcOriginsParams { window_id: String, origins: Option<Vec<String>> }
+
+pub fn op_set_ipc_allowed_origins(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<SetIpcOriginsParams>(params) {
+        Ok(p) => {
+            if let Some(s) = app.page_settings.get(&p.window_id) {
+                *s.ipc_origins.borrow_mut() = p.origins;
+                let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+            } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+// Ids of requests made by pages: "page:<window id>:<call id>"
+const PAGE_CALL_PREFIX: &str = "page:";
+
+// Methods that widen what pages can reach or decide on the app's behalf; not even a `*` capability grants them
+const NODE_ONLY: &[&str] = &[
+    // New windows carry their own capabilities and allowlists
+    "createWindow",
+    // The window's own IPC allowlist and drop delivery (real file paths)
+    "webview.setIpcAllowedOrigins",
+    "webview.setDragDrop",
+    // Which URL schemes shell.openExternal will hand to the OS
+    "shell.setAllowedSchemes",
+    // Roots the fs.* methods may touch
+    "fs.allow",
+    // Run or trash any absolute path, outside the fs.* roots
+    "shell.openPath",
+    "shell.openWith",
+    "shell.trashItem",
+    // Schema and defaults every writer of a store is checked against
+    "store.configure",
+    // Accepting downloads and choosing where they are written
+    "download.respond",
+    // Whether clipboard contents outlive the app
+    "clipboard.setHandoffOnExit",
+    // Exits the core when another instance holds the lock
+    "app.requestSingleInstanceLock",
+    // The identity pages act under
+    "app.setName",
+];
+
+/// Error code for page calls refused by the window's capabilities or IPC allowlist.
+pub const PERMISSION_DENIED: i32 = -33070;
+
+/// Queue a page's `nanoframe.invoke` call on the command channel so it goes through the same
+/// dispatcher (and permission check) as Node's requests. Calls always target the caller's window.
+fn invoke(app: &App, window_id: &str, payload: &Value, origin: &str) {
+    let Some(call_id) = payload.get("callId").and_then(|v| v.as_str()) else { return };
+    let id = json!(format!("{}{}:{}", PAGE_CALL_PREFIX, window_id, call_id));
+    // A page the window's IPC allowlist rejects gets no more access than it would get to Node
+    if !app.page_settings.get(window_id).is_some_and(|s| s.origin_allowed(origin)) {
+        let _ = app.tx_out.send(RpcResponse::error(RpcId::from_value(id), PERMISSION_DENIED, format!("Origin {} may not call core methods", origin)));
+        return;
+    }
+    let mut params = payload.get("params").cloned().unwrap_or(json!({}));
+    if let Some(obj) = params.as_object_mut() {
+        obj.insert("windowId".into(), json!(window_id));
+        // Pages act under the identity Node set, never another app's (stores, secrets, appData paths)
+        obj.remove("appName");
+        obj.remove("app_name");
+    }
+    let method = payload.get("method").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let _ = app.tx_cmd.send(RpcRequest { jsonrpc: "2.0".into(), id: Some(id), method, params, caller: Some(window_id.to_string()) });
+}
+
+/// Check a page's request against its window's capabilities; a denied call is answered with a
+/// permission error, logged to stderr and reported to Node as `webview.permissionDenied`.
+pub fn check_permission(app: &App, req: &RpcRequest) -> bool {
+    let Some(window_id) = req.caller.as_deref() else { return true };
+    let granted = !NODE_ONLY.contains(&req.method.as_str()) && app.page_settings.get(window_id).is_some_and(|s| s.permits(&req.method));
+    if granted { return true; }
+    eprintln!("nanoframe: denied {} to the page in window {}", req.method, window_id);
+    let id = RpcId::from_value(req.id.clone().unwrap_or(Value::Null));
+    let _ = app.tx_out.send(RpcResponse::error(id, PERMISSION_DENIED, format!("Permission denied: {}", req.method)));
+    let _ = app.tx_out.send(RpcResponse::notify("webview.permissionDenied", json!({ "windowId": window_id, "method": req.method })));
+    false
+}
+
+/// Split off replies to page calls (see `invoke`) so the writer thread hands them back to the
+/// event loop instead of Node.
+pub fn page_reply(resp: RpcResponse) -> std::result::Result<Internal, RpcResponse> {
+    let (id, ok, value) = match &resp {
+        RpcResponse::Result { id: RpcId::String(id), result, .. } => (id, true, result.clone()),
+        RpcResponse::Error { id: RpcId::String(id), error, .. } => (id, false, json!({ "code": error.code, "message": error.message })),
+        _ => return Err(resp),
+    };
+    // Window ids are uuids, so the first ':' after the prefix ends the window id
+    match id.strip_prefix(PAGE_CALL_PREFIX).and_then(|rest| rest.split_once(':')) {
+        Some((window_id, call_id)) => Ok(Internal::PageReply { window_id: window_id.to_string(), call_id: call_id.to_string(), ok, value }),
+        None => Err(resp),
+    }
+}
+
+/// Settle a pending `nanoframe.invoke` promise in the page.
+pub fn resolve_page_call(app: &App, window_id: &str, call_id: &str, ok: bool, value: &Value) {
+    let Some(wv) = app.webviews.get(window_id) else { return };
+    let script = format!("window.__nanoframeResolve && window.__nanoframeResolve({}, {}, {});", json!(call_id), ok, value);
+    let _ = wv.evaluate_script(&script);
+}
+
+/// Act on a message from an injected script; called on the event loop thread. `origin` is the
+/// sending document's, as reported by the webview.
+pub fn handle_message(app: &mut App, window_id: &str, payload: &Value, origin: &str) {
+    let Some(win) = app.windows.get(window_id) else { return };
+    let allowed = app.page_settings.get(window_id).is_some_and(|s| s.origin_allowed(origin));
+    match payload.get("__nanoframe").and_then(|v| v.as_str()) {
+        Some("invoke") => invoke(app, window_id, payload, origin),
+        // Pages the IPC allowlist rejects can't move or resize the window either
+        _ if !allowed => {}
+        // Failures mean the button was already released or the platform can't do it; nothing to report
+        Some("drag") => { let _ = win.drag_window(); }
+        Some("resize") => {
+            if let Some(dir) = payload.get("edge").and_then(|v| v.as_str()).and_then(parse_edge) { let _ = win.drag_resize_window(dir); }
+        }
+        Some("toggleMaximize") => {
+            if win.is_resizable() { win.set_maximized(!win.is_maximized()); }
+        }
+        _ => {}
+    }
+}
+
+/// Emit a file drag-and-drop event to Node, and to the page when enabled. Positions arrive in
+/// physical pixels relative to the webview and are reported in CSS pixels.
+pub fn handle_drag_drop(app: &mut App, window_id: &str, event: DragDropEvent) {
+    let Some(win) = app.windows.get(window_id) else { return };
+    let scale = win.scale_factor();
+    let paths = |paths: Vec<std::path::PathBuf>| paths.into_iter().map(|p| p.to_string_lossy().to_string()).collect::<Vec<_>>();
+    let (kind, mut detail) = match event {
+        DragDropEvent::Enter { paths: p, position: (x, y) } => ("dragEnter", json!({ "paths": paths(p), "x": x as f64 / scale, "y": y as f64 / scale })),
+        DragDropEvent::Over { position: (x, y) } => ("dragOver", json!({ "x": x as f64 / scale, "y": y as f64 / scale })),
+        DragDropEvent::Drop { paths: p, position: (x, y) } => ("drop", json!({ "paths": paths(p), "x": x as f64 / scale, "y": y as f64 / scale })),
+        DragDropEvent::Leave => ("dragLeave", json!({})),
+        _ => return,
+    };
+    let to_page = app.page_settings.get(window_id).is_some_and(|s| s.drop_to_page.get());
+    if let (true, Some(wv)) = (to_page, app.webviews.get(window_id)) {
+        let script = format!("window.dispatchEvent(new CustomEvent('nanoframe-{}', {{ detail: {} }}));", kind.to_lowercase(), detail);
+        let _ = wv.evaluate_script(&script);
+    }
+    detail["windowId"] = json!(window_id);
+    let _ = app.tx_out.send(RpcResponse::notify(&format!("webview.{}", kind), detail));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetDragDropParams { window_id: String, enabled: Option<bool>, to_page: Option<bool> }
+
+pub fn op_set_drag_drop(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<SetDragDropParams>(params) {
+        Ok(p) => {
+            if let Some(s) = app.page_settings.get(&p.window_id) {
+                if let Some(v) = p.enabled { s.drag_drop.set(v); }
+                if let Some(v) = p.to_page { s.drop_to_page.set(v); }
+                let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+            } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin(url: &str) -> String { origin_of(&url.parse::<Uri>().unwrap()) }
+
+    #[test]
+    fn origins_keep_scheme_host_and_port() {
+        assert_eq!(origin("https://example.com/a/b?c#d"), "https://example.com");
+        assert_eq!(origin("http://localhost:5173/"), "http://localhost:5173");
+        assert_eq!(origin("nanoframe-1a2b3c4d://localhost/"), "nanoframe-1a2b3c4d://localhost");
+    }
+
+    #[test]
+    fn origins_drop_credentials() {
+        assert_eq!(origin("https://user:pw@example.com/"), "https://example.com");
+    }
+
+    #[test]
+    fn opaque_documents_have_a_null_origin() {
+        assert_eq!(origin("/relative/path"), "null");
+        assert_eq!(origin_of(&Uri::default()), "null");
+    }
+
+    #[test]
+    fn allowlist_matches_exact_origins_and_wildcards() {
+        let allowed = vec!["https://app.example.com".to_string(), "https://*.trusted.org".to_string()];
+        assert!(origin_allowed(&allowed, "https://app.example.com"));
+        assert!(!origin_allowed(&allowed, "https://app.example.com:8443"));
+        assert!(!origin_allowed(&allowed, "http://app.example.com"));
+        assert!(origin_allowed(&allowed, "https://a.trusted.org"));
+        assert!(origin_allowed(&allowed, "https://a.b.trusted.org"));
+        assert!(!origin_allowed(&allowed, "https://trusted.org"));
+        assert!(!origin_allowed(&allowed, "https://eviltrusted.org"));
+        assert!(!origin_allowed(&allowed, "http://a.trusted.org"));
+        assert!(!origin_allowed(&allowed, "null"));
+        assert!(origin_allowed(&["*".to_string()], "null"));
+        assert!(!origin_allowed(&[], "https://app.example.com"));
+    }
+
+    #[test]
+    fn capabilities_match_exact_names_prefixes_and_star() {
+        let settings = |caps: &[&str]| PageSettings {
+            drag_drop: Cell::new(false),
+            drop_to_page: Cell::new(false),
+            ipc_origins: RefCell::new(None),
+            capabilities: caps.iter().map(|c| c.to_string()).collect(),
+        };
+        let s = settings(&["clipboard.readText", "store.*"]);
+        assert!(s.permits("clipboard.readText"));
+        assert!(!s.permits("clipboard.writeText"));
+        assert!(s.permits("store.get"));
+        assert!(!s.permits("storex.get"));
+        assert!(settings(&["*"]).permits("fs.readFile"));
+        // A bare prefix without the dot grants nothing
+        assert!(!settings(&["store*"]).permits("store.get"));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::rpc::RpcResponse;
+use anyhow::{anyhow, Result};
+use crossbeam_channel::{bounded, unbounded, RecvTimeoutError, Sender};
+use serde::Deserialize;
+use serde_json::json;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Which selection a clipboard op targets. PRIMARY only exists on Linux (X11/Wayland).
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Selection { #[default] Clipboard, Primary }
+
+impl Selection {
+    #[cfg(target_os = "linux")]
+    pub fn kind(self) -> Result<arboard::LinuxClipboardKind> {
+        Ok(match self { Selection::Clipboard => arboard::LinuxClipboardKind::Clipboard, Selection::Primary => arboard::LinuxClipboardKind::Primary })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn kind(self) -> Result<()> {
+        match self { Selection::Clipboard => Ok(()), Selection::Primary => Err(anyhow!("PRIMARY selection is only available on Linux")) }
+    }
+
+    pub fn as_str(self) -> &'static str { match self { Selection::Clipboard => "clipboard", Selection::Primary => "primary" } }
+}
+
+pub fn cb_get(cb: &mut arboard::Clipboard, sel: Selection) -> Result<arboard::Get<'_>> {
+    #[cfg(target_os = "linux")]
+    { use arboard::GetExtLinux; Ok(cb.get().clipboard(sel.kind()?)) }
+    #[cfg(not(target_os = "linux"))]
+    { sel.kind()?; Ok(cb.get()) }
+}
+
+pub fn cb_set(cb: &mut arboard::Clipboard, sel: Selection) -> Result<arboard::Set<'_>> {
+    #[cfg(target_os = "linux")]
+    { use arboard::SetExtLinux; Ok(cb.set().clipboard(sel.kind()?)) }
+    #[cfg(not(target_os = "linux"))]
+    { sel.kind()?; Ok(cb.set()) }
+}
+
+pub fn cb_clear(cb: &mut arboard::Clipboard, sel: Selection) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    { use arboard::ClearExtLinux; cb.clear_with().clipboard(sel.kind()?)?; }
+    #[cfg(not(target_os = "linux"))]
+    { sel.kind()?; cb.clear()?; }
+    Ok(())
+}
+
+// Cheap change token so the poller only notifies on actual changes, without reading the contents
+// every tick: the system change counter where there is one, a hash of the contents on Linux (X11
+// and Wayland have no counter).
+#[cfg(target_os = "windows")]
+fn change_token(_cb: &mut arboard::Clipboard, _sel: Selection) -> u64 {
+    unsafe { windows_sys::Win32::System::DataExchange::GetClipboardSequenceNumber() as u64 }
+}
+
+#[cfg(target_os = "macos")]
+fn change_token(_cb: &mut arboard::Clipboard, _sel: Selection) -> u64 {
+    objc2_app_kit::NSPasteboard::generalPasteboard().changeCount() as u64
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn change_token(cb: &mut arboard::Clipboard, sel: Selection) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let mut h = DefaultHasher::new();
+    let text = cb_get(cb, sel).ok().and_then(|g| g.text().ok());
+    text.hash(&mut h);
+    cb_get(cb, sel).ok().and_then(|g| g.html().ok()).hash(&mut h);
+    cb_get(cb, sel).ok().and_then(|g| g.file_list().ok()).hash(&mut h);
+    // An image means a full transfer, so it is only read when there is no text to tell copies apart
+    if text.is_none() {
+        if let Some(img) = cb_get(cb, sel).ok().and_then(|g| g.image().ok()) {
+            (img.width, img.height, &img.bytes).hash(&mut h);
+        }
+    }
+    h.finish()
+}
+
+// What `clipboard.changed` carries; the contents are only read when the caller asked for them
+fn change_event(cb: &mut arboard::Clipboard, sel: Selection, include_content: bool) -> serde_json::Value {
+    if !include_content { return json!({ "selection": sel.as_str() }); }
+    let text = cb_get(cb, sel).ok().and_then(|g| g.text().ok());
+    let has_image = cb_get(cb, sel).ok().and_then(|g| g.image().ok()).is_some();
+    json!({ "selection": sel.as_str(), "text": text, "hasImage": has_image })
+}
+
+/// Change poller settings: selection, poll interval and whether events include the contents.
+#[derive(Clone, Copy)]
+pub struct Watch { pub selection: Selection, pub interval: Duration, pub include_content: bool }
+
+type Job = Box<dyn FnOnce(&mut Option<arboard::Clipboard>) + Send>;
+
+enum Msg {
+    Run(Job),
+    Watch(Option<Watch>),
+    Shutdown { handoff: bool, done: Sender<()> },
+}
+
+/// Owns a single `arboard::Clipboard` on a dedicated thread for the lifetime of the process.
+///
+/// On X11/Wayland the process that last wrote a selection has to keep serving it, so dropping
+/// the handle right after a write can make the content vanish. Every clipboard op is funneled
+/// through this thread instead, and the handle is only dropped on shutdown.
+pub struct ClipboardService {
+    tx: Sender<Msg>,
+    thread: Option<JoinHandle<()>>,
+    watching: bool,
+    // Drop the handle on exit so X11 hands the selection to a clipboard manager (if any)
+    pub handoff_on_exit: bool,
+}
+
+impl ClipboardService {
+    pub fn spawn(tx_out: Sender<RpcResponse>) -> Self {
+        let (tx, rx) = unbounded::<Msg>();
+        let thread = std::thread::spawn(move || {
+            // Opened lazily so apps that never touch the clipboard don't need a display connection for it
+            let mut slot: Option<arboard::Clipboard> = None;
+            let mut watch: Option<Watch> = None;
+            let mut last = 0u64;
+            let mut next_poll = Instant::now();
+            loop {
+                let msg = match watch {
+                    Some(_) => rx.recv_deadline(next_poll),
+                    None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                match msg {
+                    Ok(Msg::Run(job)) => job(&mut slot),
+                    Ok(Msg::Watch(w)) => {
+                        watch = w;
+                        if let Some(w) = watch {
+                            if slot.is_none() { slot = arboard::Clipboard::new().ok(); }
+                            last = slot.as_mut().map(|cb| change_token(cb, w.selection)).unwrap_or(0);
+                            next_poll = Instant::now() + w.interval;
+                        }
+                    }
+                    Ok(Msg::Shutdown { handoff, done }) => {
+                        if handoff { drop(slot.take()); } else { std::mem::forget(slot.take()); }
+                        let _ = done.send(());
+                        break;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        let Some(w) = watch else { continue };
+                        next_poll = Instant::now() + w.interval;
+                        if slot.is_none() { slot = arboard::Clipboard::new().ok(); }
+                        let Some(cb) = slot.as_mut() else { continue };
+                        let token = change_token(cb, w.selection);
+                        if token != last {
+                            last = token;
+                            let _ = tx_out.send(RpcResponse::notify("clipboard.changed", change_event(cb, w.selection, w.include_content)));
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+        Self { tx, thread: Some(thread), watching: false, handoff_on_exit: true }
+    }
+
+    /// Runs `f` against the shared clipboard handle on the service thread and waits for the result.
+    pub fn run<T, F>(&self, f: F) -> Result<T>
+    where T: Send + 'static, F: FnOnce(&mut arboard::Clipboard) -> Result<T> + Send + 'static {
+        let (tx, rx) = bounded::<Result<T>>(1);
+        let job: Job = Box::new(move |slot| {
+            let r = match slot {
+                Some(cb) => f(cb),
+                None => match arboard::Clipboard::new() { Ok(cb) => f(slot.insert(cb)), Err(e) => Err(e.into()) },
+            };
+            let _ = tx.send(r);
+        });
+        self.tx.send(Msg::Run(job)).map_err(|_| anyhow!("Clipboard service stopped"))?;
+        rx.recv().map_err(|_| anyhow!("Clipboard service stopped"))?
+    }
+
+    pub fn watch(&mut self, watch: Watch) {
+        self.watching = true;
+        let _ = self.tx.send(Msg::Watch(Some(watch)));
+    }
+
+    /// Stops the change poller; returns whether one was running.
+    pub fn unwatch(&mut self) -> bool {
+        let _ = self.tx.send(Msg::Watch(None));
+        std::mem::replace(&mut self.watching, false)
+    }
+
+    /// Releases the clipboard before the process exits. Bounded so a stuck handover can't hang shutdown.
+    pub fn shutdown(&mut self) {
+        let Some(thread) = self.thread.take() else { return };
+        let (done_tx, done_rx) = bounded::<()>(1);
+        if self.tx.send(Msg::Shutdown { handoff: self.handoff_on_exit, done: done_tx }).is_ok()
+            && done_rx.recv_timeout(Duration::from_secs(3)).is_ok()
+        {
+            let _ = thread.join();
+        }
+    }
+}
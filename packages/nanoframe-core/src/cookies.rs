@@ -0,0 +1,178 @@
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use wry::cookie::time::OffsetDateTime;
+use wry::cookie::{Cookie, SameSite};
+use wry::WebView;
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum SameSiteParam { Strict, Lax, None }
+
+/// Cookie as accepted from JS (`cookies.set`, `createWindow({ cookies })`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CookieParam {
+    name: String,
+    value: String,
+    // Defaults to the host of `url` (or the window's url when seeding)
+    domain: Option<String>,
+    path: Option<String>,
+    secure: Option<bool>,
+    http_only: Option<bool>,
+    // Unix time in seconds; omitted makes a session cookie
+    expires: Option<f64>,
+    same_site: Option<SameSiteParam>,
+    url: Option<String>,
+}
+
+// Host part of an absolute URL, without userinfo or port
+fn host_of(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = if host.starts_with('[') { host.split_inclusive(']').next()? } else { host.split(':').next()? };
+    (!host.is_empty()).then_some(host)
+}
+
+/// Build a cookie; `default_url` supplies the domain/secure defaults when the cookie has no `url`.
+pub(crate) fn build_cookie(p: &CookieParam, default_url: Option<&str>) -> Result<Cookie<'static>> {
+    let url = p.url.as_deref().or(default_url);
+    let domain = match p.domain.as_deref() {
+        Some(d) => d.to_string(),
+        None => url.and_then(host_of).ok_or_else(|| anyhow!("Cookie '{}' needs a domain or url", p.name))?.to_string(),
+    };
+    let mut b = Cookie::build((p.name.clone(), p.value.clone())).domain(domain).path(p.path.clone().unwrap_or_else(|| "/".into()));
+    b = b.secure(p.secure.unwrap_or_else(|| url.is_some_and(|u| u.starts_with("https://"))));
+    if let Some(v) = p.http_only { b = b.http_only(v); }
+    if let Some(secs) = p.expires { b = b.expires(OffsetDateTime::from_unix_timestamp(secs as i64)?); }
+    if let Some(s) = p.same_site { b = b.same_site(match s { SameSiteParam::Strict => SameSite::Strict, SameSiteParam::Lax => SameSite::Lax, SameSiteParam::None => SameSite::None }); }
+    Ok(b.build())
+}
+
+fn cookie_json(c: &Cookie) -> Value {
+    json!({
+        "name": c.name(),
+        "value": c.value(),
+        "domain": c.domain(),
+        "path": c.path(),
+        "secure": c.secure().unwrap_or(false),
+        "httpOnly": c.http_only().unwrap_or(false),
+        "expires": c.expires_datetime().map(|t| t.unix_timestamp()),
+        "sameSite": c.same_site().map(|s| s.to_string().to_lowercase()),
+    })
+}
+
+// The cookie store belongs to a web context, so a profile is reached through any window using it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreParams { window_id: Option<String>, profile: Option<String>, app_name: Option<String> }
+
+fn store<'a>(app: &'a App, s: &StoreParams, id: &RpcId) -> Option<&'a WebView> {
+    let window_id = match (s.window_id.as_deref(), s.profile.as_deref()) {
+        (Some(w), _) => Some(w),
+        (None, Some(name)) => app.profiles.window_for(name, app.identity(s.app_name.as_deref())),
+        (None, None) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -32602, "windowId or profile is required".into())); return None; }
+    };
+    let wv = window_id.and_then(|w| app.webviews.get(w));
+    if wv.is_none() { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -32001, "Window not found".into())); }
+    wv
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetCookiesParams {
+    #[serde(flatten)]
+    store: StoreParams,
+    url: String,
+    name: Option<String>,
+}
+
+pub fn op_cookies_get(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<GetCookiesParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some(wv) = store(app, &p.store, &id) else { return };
+    match wv.cookies_for_url(&p.url) {
+        Ok(list) => {
+            let list: Vec<Value> = list.iter().filter(|c| p.name.as_deref().is_none_or(|n| c.name() == n)).map(cookie_json).collect();
+            let _ = app.tx_out.send(RpcResponse::result(id, json!(list)));
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33060, e.to_string())); }
+    }
+}
+
+pub fn op_cookies_get_all(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<StoreParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some(wv) = store(app, &p, &id) else { return };
+    match wv.cookies() {
+        Ok(list) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(list.iter().map(cookie_json).collect::<Vec<_>>()))); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33060, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetCookieParams {
+    #[serde(flatten)]
+    store: StoreParams,
+    cookie: CookieParam,
+}
+
+pub fn op_cookies_set(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<SetCookieParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let cookie = match build_cookie(&p.cookie, None) {
+        Ok(c) => c,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some(wv) = store(app, &p.store, &id) else { return };
+    match wv.set_cookie(&cookie) {
+        Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33060, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteCookieParams {
+    #[serde(flatten)]
+    store: StoreParams,
+    name: String,
+    // Restrict to cookies sent to this url; otherwise match on domain/path (if given)
+    url: Option<String>,
+    domain: Option<String>,
+    path: Option<String>,
+}
+
+pub fn op_cookies_delete(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<DeleteCookieParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some(wv) = store(app, &p.store, &id) else { return };
+    // Deletion needs the exact name/domain/path triple, so look the stored cookies up first
+    let found = match p.url.as_deref() { Some(url) => wv.cookies_for_url(url), None => wv.cookies() };
+    let matches = |c: &Cookie| {
+        c.name() == p.name
+            && p.domain.as_deref().is_none_or(|d| c.domain().is_some_and(|cd| cd.trim_start_matches('.') == d.trim_start_matches('.')))
+            && p.path.as_deref().is_none_or(|path| c.path() == Some(path))
+    };
+    let res = found.and_then(|list| {
+        let targets: Vec<_> = list.into_iter().filter(|c| matches(c)).collect();
+        for c in &targets { wv.delete_cookie(c)?; }
+        Ok(targets.len())
+    });
+    match res {
+        Ok(n) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(n))); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33060, e.to_string())); }
+    }
+}
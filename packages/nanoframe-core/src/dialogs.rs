@@ -2,9 +2,11 @@ use crate::rpc::{RpcId, RpcResponse};
 use crate::state::App;
 use directories::BaseDirs;
 use directories::ProjectDirs;
+use directories::UserDirs;
 use rfd::FileDialog;
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Deserialize)]
 struct FileFilter { name: Option<String>, extensions: Option<Vec<String>> }
@@ -36,6 +38,8 @@ pub fn op_open_dialog(app: &mut App, params: Value, id: RpcId) {
                 if multiple { dlg.pick_files().unwrap_or_default().into_iter().map(|p| p.to_string_lossy().to_string()).collect::<Vec<_>>() }
                 else { dlg.pick_file().map(|p| vec![p.to_string_lossy().to_string()]).unwrap_or_default() }
             };
+            // What the user picked becomes reachable through fs.*
+            for path in &result { app.fs_scope.allow(Path::new(path)); }
             let _ = app.tx_out.send(RpcResponse::result(id, json!({ "paths": result })));
         }
         Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
@@ -52,27 +56,35 @@ pub fn op_save_dialog(app: &mut App, params: Value, id: RpcId) {
             if let Some(t) = p.title { dlg = dlg.set_title(&t); }
             if let Some(name) = p.default_file_name { dlg = dlg.set_file_name(&name); }
             let result = dlg.save_file().map(|p| p.to_string_lossy().to_string());
+            if let Some(path) = result.as_deref() { app.fs_scope.allow(Path::new(path)); }
             let _ = app.tx_out.send(RpcResponse::result(id, json!({ "path": result })));
         }
         Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
     }
 }
 
+pub(crate) const DEFAULT_APP_NAME: &str = "nanoframe-app";
+
+/// Resolve one of the `app.getPath` names; app-specific dirs are keyed by `app_name`.
+pub(crate) fn app_path(name: &str, app_name: Option<&str>) -> Option<PathBuf> {
+    let app_name = app_name.unwrap_or(DEFAULT_APP_NAME);
+    match name {
+        "home" => BaseDirs::new().map(|b| b.home_dir().to_path_buf()),
+        "temp" => Some(std::env::temp_dir()),
+        "downloads" => UserDirs::new().and_then(|u| u.download_dir().map(|d| d.to_path_buf())),
+        "appData" => ProjectDirs::from("", "", app_name).map(|p| p.data_dir().to_path_buf()),
+        "userData" => ProjectDirs::from("", "", app_name).map(|p| p.data_dir().join("User Data")),
+        _ => None,
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct GetPathParams { name: String, app_name: Option<String> }
 
 pub fn op_app_get_path(app: &mut App, params: Value, id: RpcId) {
     let res = match serde_json::from_value::<GetPathParams>(params) {
         Ok(p) => {
-            let name = p.name.as_str();
-            let app_name = p.app_name.as_deref().unwrap_or("nanoframe-app");
-            let val = match name {
-                "home" => BaseDirs::new().map(|b| b.home_dir().to_path_buf()),
-                "temp" => Some(std::env::temp_dir()),
-                "appData" => ProjectDirs::from("", "", app_name).map(|p| p.data_dir().to_path_buf()),
-                "userData" => ProjectDirs::from("", "", app_name).map(|p| p.data_dir().join("User Data")),
-                _ => None,
-            };
+            let val = app_path(&p.name, app.identity(p.app_name.as_deref()));
             json!({ "path": val.map(|p| p.to_string_lossy().to_string()) })
         }
         Err(e) => {
@@ -82,3 +94,17 @@ pub fn op_app_get_path(app: &mut App, params: Value, id: RpcId) {
     };
     let _ = app.tx_out.send(RpcResponse::result(id, res));
 }
+
+#[derive(Debug, Deserialize)]
+struct SetNameParams { name: String }
+
+pub fn op_app_set_name(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<SetNameParams>(params) {
+        Ok(p) if !p.name.trim().is_empty() => {
+            app.app_name = Some(p.name);
+            let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+        }
+        Ok(_) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, "name must not be empty".into())); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
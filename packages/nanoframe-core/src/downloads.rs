@@ -0,0 +1,292 @@
+use crate::bridge::origin_of;
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::{App, Internal};
+use crossbeam_channel::Sender;
+use rfd::FileDialog;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use wry::http::Uri;
+use wry::WebViewBuilder;
+
+// Held-back downloads Node hasn't answered within this time are dropped as cancelled
+const DECISION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+// An accepted download whose repeat request doesn't arrive within this time is reported as failed
+const REISSUE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What happens when a page starts a download.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DownloadMode {
+    /// Save to the download directory under the suggested name (browser default)
+    #[default]
+    Auto,
+    /// Ask the user with a save dialog (shown from the event loop; the download is re-issued once saved)
+    Dialog,
+    /// Refuse every download
+    Deny,
+    /// Hold the download until Node answers with `download.respond`, then re-issue it
+    Node,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum DecisionAction { Accept, Deny, Dialog }
+
+/// Node's answer to a `download.started` notification in "node" mode.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadDecision {
+    download_id: String,
+    action: DecisionAction,
+    // Absolute destination for "accept", initial location for "dialog"
+    path: Option<String>,
+}
+
+// Extensions kept whole when numbering a file, so "a.tar.gz" becomes "a (1).tar.gz"
+const DOUBLE_EXTENSIONS: [&str; 6] = [".tar.gz", ".tar.bz2", ".tar.xz", ".tar.zst", ".user.js", ".d.ts"];
+
+// Split "name.ext" on the last extension; dotfiles like ".bashrc" have none
+fn split_extension(file_name: &str) -> (&str, &str) {
+    let lower = file_name.to_ascii_lowercase();
+    if let Some(ext) = DOUBLE_EXTENSIONS.iter().find(|e| lower.len() > e.len() && lower.ends_with(*e)) {
+        return file_name.split_at(file_name.len() - ext.len());
+    }
+    match file_name.rfind('.') { Some(i) if i > 0 => file_name.split_at(i), _ => (file_name, "") }
+}
+
+// First free "name (n).ext" in `dir`, matching what the webviews do for their default directory
+fn unique_path(dir: &Path, file_name: &str) -> PathBuf {
+    let (stem, ext) = split_extension(file_name);
+    let mut path = dir.join(file_name);
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{} ({}){}", stem, n, ext));
+        n += 1;
+    }
+    path
+}
+
+fn save_dialog(dest: &mut PathBuf) -> bool {
+    let mut dlg = FileDialog::new();
+    if let Some(dir) = dest.parent() { dlg = dlg.set_directory(dir); }
+    if let Some(name) = dest.file_name() { dlg = dlg.set_file_name(name.to_string_lossy()); }
+    match dlg.save_file() { Some(p) => { *dest = p; true } None => false }
+}
+
+fn apply_decision(d: DownloadDecision, dest: &mut PathBuf) -> bool {
+    match (d.action, d.path) {
+        (DecisionAction::Deny, _) => false,
+        (DecisionAction::Accept, None) => true,
+        // Paths were checked to be absolute in op_download_respond
+        (DecisionAction::Accept, Some(p)) => { *dest = PathBuf::from(p); true }
+        (DecisionAction::Dialog, path) => {
+            if let Some(p) = path { *dest = PathBuf::from(p); }
+            save_dialog(dest)
+        }
+    }
+}
+
+/// A download refused on the spot while Node or the user decides on it.
+struct Pending { window_id: String, url: String, dest: PathBuf, since: Instant }
+
+/// An accepted download waiting for its repeat request; used at most once.
+struct Approved { download_id: String, dest: PathBuf, since: Instant }
+
+/// Downloads in "dialog" and "node" mode. The webview callback can't wait for an answer without
+/// freezing every window, so the download is cancelled right away and, once accepted, started
+/// again from the page; the repeat request then goes through with the chosen destination.
+#[derive(Default)]
+pub struct DownloadQueue {
+    pending: HashMap<String, Pending>,
+    // By (window id, url)
+    approved: HashMap<(String, String), Approved>,
+}
+
+fn cancelled(tx_out: &Sender<RpcResponse>, window_id: &str, download_id: &str, url: &str) {
+    let _ = tx_out.send(RpcResponse::notify("download.completed", json!({ "windowId": window_id, "downloadId": download_id, "url": url, "path": null, "success": false, "cancelled": true })));
+}
+
+fn failed(tx_out: &Sender<RpcResponse>, window_id: &str, download_id: &str, url: &str, error: &str) {
+    let _ = tx_out.send(RpcResponse::notify("download.completed", json!({ "windowId": window_id, "downloadId": download_id, "url": url, "path": null, "success": false, "cancelled": false, "error": error })));
+}
+
+// Whether clicking a download link to `url` in the page at `page_url` downloads it: links to other
+// origins (and blob: URLs, which the page may have revoked) navigate or fail instead
+fn reissuable(url: &str, page_url: Option<&str>) -> bool {
+    if url.get(..5).is_some_and(|s| s.eq_ignore_ascii_case("data:")) { return true; }
+    let (Ok(url), Some(Ok(page))) = (url.parse::<Uri>(), page_url.map(str::parse::<Uri>)) else { return false };
+    matches!(url.scheme_str(), Some("http" | "https")) && origin_of(&url) == origin_of(&page)
+}
+
+/// Install download handlers emitting `download.started` / `download.completed` for `window_id`.
+pub(crate) fn attach<'a>(
+    wvb: WebViewBuilder<'a>,
+    window_id: &str,
+    mode: DownloadMode,
+    directory: Option<PathBuf>,
+    tx_out: Sender<RpcResponse>,
+    tx_internal: Sender<Internal>,
+    queue: Rc<RefCell<DownloadQueue>>,
+) -> WebViewBuilder<'a> {
+    // Completion only reports the url, so in-flight download ids are queued per url
+    let in_flight: Rc<RefCell<HashMap<String, VecDeque<String>>>> = Rc::default();
+    let started = {
+        let (tx, in_flight, window_id) = (tx_out.clone(), in_flight.clone(), window_id.to_string());
+        move |url: String, dest: &mut PathBuf| -> bool {
+            let mut queue = queue.borrow_mut();
+            // The repeat of a download that was accepted meanwhile
+            if let Some(approved) = queue.approved.remove(&(window_id.clone(), url.clone())) {
+                *dest = approved.dest;
+                in_flight.borrow_mut().entry(url).or_default().push_back(approved.download_id);
+                return true;
+            }
+            let download_id = Uuid::new_v4().to_string();
+            if let (Some(dir), Some(name)) = (directory.as_deref(), dest.file_name()) { *dest = unique_path(dir, &name.to_string_lossy()); }
+            let suggested = dest.file_name().map(|n| n.to_string_lossy().to_string());
+            let _ = tx.send(RpcResponse::notify("download.started", json!({
+                "windowId": window_id, "downloadId": download_id, "url": url, "suggestedFilename": suggested, "path": dest.to_string_lossy(), "awaitingDecision": mode == DownloadMode::Node,
+            })));
+            match mode {
+                DownloadMode::Auto => {
+                    in_flight.borrow_mut().entry(url).or_default().push_back(download_id);
+                    true
+                }
+                DownloadMode::Deny => { cancelled(&tx, &window_id, &download_id, &url); false }
+                DownloadMode::Dialog | DownloadMode::Node => {
+                    queue.pending.insert(download_id.clone(), Pending { window_id: window_id.clone(), url, dest: dest.clone(), since: Instant::now() });
+                    if mode == DownloadMode::Dialog { let _ = tx_internal.send(Internal::DownloadDialog { download_id }); }
+                    false
+                }
+            }
+        }
+    };
+    let completed = {
+        let window_id = window_id.to_string();
+        move |url: String, path: Option<PathBuf>, success: bool| {
+            // Downloads refused above were already reported
+            let Some(download_id) = in_flight.borrow_mut().get_mut(&url).and_then(|q| q.pop_front()) else { return };
+            in_flight.borrow_mut().retain(|_, q| !q.is_empty());
+            let _ = tx_out.send(RpcResponse::notify("download.completed", json!({
+                "windowId": window_id, "downloadId": download_id, "url": url, "path": path.map(|p| p.to_string_lossy().to_string()), "success": success, "cancelled": false,
+            })));
+        }
+    };
+    wvb.with_download_started_handler(started).with_download_completed_handler(completed)
+}
+
+// Start an accepted download again from its page, or report it cancelled or failed
+fn finish(app: &App, download_id: String, pending: Pending, dest: Option<PathBuf>) {
+    let webview = app.webviews.get(&pending.window_id);
+    let (Some(dest), Some(webview)) = (dest, webview) else { return cancelled(&app.tx_out, &pending.window_id, &download_id, &pending.url) };
+    if !reissuable(&pending.url, webview.url().ok().as_deref()) {
+        return failed(&app.tx_out, &pending.window_id, &download_id, &pending.url, "Only same-origin and data: downloads can be started again after a decision");
+    }
+    let script = format!("(() => {{ const a = document.createElement('a'); a.href = {}; a.download = ''; document.documentElement.appendChild(a); a.click(); a.remove(); }})();", json!(pending.url));
+    if webview.evaluate_script(&script).is_err() {
+        return failed(&app.tx_out, &pending.window_id, &download_id, &pending.url, "The page could not start the download again");
+    }
+    let key = (pending.window_id.clone(), pending.url.clone());
+    let approved = Approved { download_id, dest, since: Instant::now() };
+    // An earlier approval for the same url whose repeat never came
+    if let Some(old) = app.downloads.borrow_mut().approved.insert(key, approved) {
+        failed(&app.tx_out, &pending.window_id, &old.download_id, &pending.url, "Superseded by a later download of the same url");
+    }
+}
+
+/// Drop held-back downloads nobody decided on in time, and approvals whose repeat request never
+/// came (e.g. the server refused it). Called on every event loop iteration.
+pub fn expire(app: &App) {
+    let mut queue = app.downloads.borrow_mut();
+    queue.pending.retain(|download_id, p| {
+        let keep = p.since.elapsed() <= DECISION_TIMEOUT;
+        if !keep { cancelled(&app.tx_out, &p.window_id, download_id, &p.url); }
+        keep
+    });
+    queue.approved.retain(|(window_id, url), a| {
+        let keep = a.since.elapsed() <= REISSUE_TIMEOUT;
+        if !keep { failed(&app.tx_out, window_id, &a.download_id, url, "The download did not start again"); }
+        keep
+    });
+}
+
+/// Show the save dialog for a "dialog" mode download, outside of the webview callback.
+pub fn show_dialog(app: &mut App, download_id: String) {
+    let Some(pending) = app.downloads.borrow_mut().pending.remove(&download_id) else { return };
+    let mut dest = pending.dest.clone();
+    let dest = save_dialog(&mut dest).then_some(dest);
+    finish(app, download_id, pending, dest);
+}
+
+pub fn op_download_respond(app: &mut App, params: Value, id: RpcId) {
+    let d = match serde_json::from_value::<DownloadDecision>(params) {
+        Ok(d) => d,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    // Checked before the download leaves the queue, so Node can answer again
+    if d.path.as_deref().is_some_and(|p| !Path::new(p).is_absolute()) {
+        let _ = app.tx_out.send(RpcResponse::error(id, -32602, "path must be absolute".into()));
+        return;
+    }
+    let Some(pending) = app.downloads.borrow_mut().pending.remove(&d.download_id) else {
+        let _ = app.tx_out.send(RpcResponse::error(id, -33065, format!("No download {} is awaiting a decision", d.download_id)));
+        return;
+    };
+    let download_id = d.download_id.clone();
+    let mut dest = pending.dest.clone();
+    let dest = apply_decision(d, &mut dest).then_some(dest);
+    finish(app, download_id, pending, dest);
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_the_last_extension() {
+        assert_eq!(split_extension("report.pdf"), ("report", ".pdf"));
+        assert_eq!(split_extension("archive.v2.zip"), ("archive.v2", ".zip"));
+        assert_eq!(split_extension("README"), ("README", ""));
+        assert_eq!(split_extension(".bashrc"), (".bashrc", ""));
+    }
+
+    #[test]
+    fn keeps_double_extensions_whole() {
+        assert_eq!(split_extension("src.tar.gz"), ("src", ".tar.gz"));
+        assert_eq!(split_extension("SRC.TAR.GZ"), ("SRC", ".TAR.GZ"));
+        assert_eq!(split_extension("index.d.ts"), ("index", ".d.ts"));
+        // Nothing left for a name
+        assert_eq!(split_extension(".tar.gz"), (".tar", ".gz"));
+    }
+
+    #[test]
+    fn numbers_taken_names_before_the_extension() {
+        let dir = std::env::temp_dir().join(format!("nanoframe-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(unique_path(&dir, "a.tar.gz"), dir.join("a.tar.gz"));
+        std::fs::write(dir.join("a.tar.gz"), b"").unwrap();
+        assert_eq!(unique_path(&dir, "a.tar.gz"), dir.join("a (1).tar.gz"));
+        std::fs::write(dir.join("a (1).tar.gz"), b"").unwrap();
+        assert_eq!(unique_path(&dir, "a.tar.gz"), dir.join("a (2).tar.gz"));
+        std::fs::write(dir.join("notes"), b"").unwrap();
+        assert_eq!(unique_path(&dir, "notes"), dir.join("notes (1)"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reissues_only_same_origin_and_data_urls() {
+        let page = Some("https://example.com/app/index.html");
+        assert!(reissuable("https://example.com/files/a.zip", page));
+        assert!(reissuable("DATA:text/plain,hi", page));
+        assert!(!reissuable("https://cdn.example.com/a.zip", page));
+        assert!(!reissuable("http://example.com/a.zip", page));
+        assert!(!reissuable("blob:https://example.com/0b6c1f3e", page));
+        assert!(!reissuable("https://example.com/a.zip", None));
+    }
+}
@@ -0,0 +1,450 @@
+use crate::dialogs::app_path;
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use base64::Engine as _;
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Paths the `fs.*` methods may touch: roots allowed by Node (`fs.allow`) and whatever the user
+/// picked in an open/save dialog. A root covers itself and everything below it.
+#[derive(Default)]
+pub struct FsScope {
+    roots: Vec<PathBuf>,
+}
+
+// `path` with symlinks resolved on its deepest existing ancestor; the missing tail is appended as is.
+// None for a dangling symlink anywhere in the tail: creating the path would follow it wherever it points.
+fn real_path(path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut tail = Vec::new();
+    let base = loop {
+        match existing.canonicalize() {
+            Ok(p) => break p,
+            Err(_) if existing.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()) => return None,
+            Err(_) => { tail.push(existing.file_name()?); existing = existing.parent()?; }
+        }
+    };
+    Some(tail.into_iter().rev().fold(base, |p, c| p.join(c)))
+}
+
+impl FsScope {
+    pub fn allow(&mut self, path: &Path) {
+        let Some(p) = real_path(path) else { return };
+        if !self.roots.contains(&p) { self.roots.push(p); }
+    }
+
+    /// Resolve a requested path, refusing `..`, relative paths and anything (symlinks included)
+    /// that ends up outside every root. With `follow` false a symlink in the last component is
+    /// kept, so it is the link that gets removed rather than its target.
+    fn resolve(&self, path: &Path, follow: bool) -> std::result::Result<PathBuf, String> {
+        if !path.is_absolute() { return Err(format!("Path must be absolute: {}", path.display())); }
+        if path.components().any(|c| c == Component::ParentDir) { return Err(format!("Path must not contain '..': {}", path.display())); }
+        let real = match (follow, path.parent(), path.file_name()) {
+            (false, Some(parent), Some(name)) => real_path(parent).map(|p| p.join(name)),
+            _ => real_path(path),
+        };
+        let real = real.ok_or_else(|| format!("Invalid path or dangling symlink: {}", path.display()))?;
+        // Roots are re-resolved too: they may not have existed when they were allowed
+        if self.roots.iter().any(|r| real.starts_with(real_path(r).as_deref().unwrap_or(r))) { Ok(real) } else { Err(format!("Path is outside the allowed roots: {}", path.display())) }
+    }
+
+    fn is_root(&self, path: &Path) -> bool { self.roots.iter().any(|r| r == path) }
+}
+
+// Every method takes an absolute `path`, or one relative to a named `root` ("appData", ...)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PathParams { path: String, root: Option<String>, app_name: Option<String> }
+
+impl PathParams {
+    fn joined(&self, app: &App) -> std::result::Result<PathBuf, String> {
+        match self.root.as_deref() {
+            None => Ok(PathBuf::from(&self.path)),
+            Some(name) => {
+                let base = app_path(name, app.identity(self.app_name.as_deref())).ok_or_else(|| format!("Unknown root: {}", name))?;
+                let rel = Path::new(&self.path);
+                if rel.is_absolute() { return Err("path must be relative when root is given".into()); }
+                Ok(base.join(rel))
+            }
+        }
+    }
+}
+
+// Parse params and resolve the path against the scope, answering the request on failure
+fn scoped<T: for<'de> Deserialize<'de>>(app: &App, params: Value, id: &RpcId, follow: bool, path: impl Fn(&T) -> &PathParams) -> Option<(T, PathBuf)> {
+    let p = match serde_json::from_value::<T>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -32602, e.to_string())); return None; }
+    };
+    match path(&p).joined(app).and_then(|full| app.fs_scope.resolve(&full, follow)) {
+        Ok(full) => Some((p, full)),
+        Err(msg) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -33090, msg)); None }
+    }
+}
+
+fn io_error(app: &App, id: RpcId, e: std::io::Error) {
+    let code = if e.kind() == std::io::ErrorKind::NotFound { -33091 } else { -33092 };
+    let _ = app.tx_out.send(RpcResponse::error(id, code, e.to_string()));
+}
+
+fn millis(t: std::io::Result<SystemTime>) -> Option<f64> {
+    t.ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs_f64() * 1000.0)
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum Encoding {
+    #[default]
+    Utf8,
+    Base64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AllowParams { path: Option<String>, root: Option<String>, app_name: Option<String> }
+
+pub fn op_fs_allow(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<AllowParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let path = match (p.path, p.root) {
+        (Some(path), None) if Path::new(&path).is_absolute() => PathBuf::from(path),
+        (None, Some(name)) => match app_path(&name, app.identity(p.app_name.as_deref())) {
+            Some(dir) => dir,
+            None => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, format!("Unknown root: {}", name))); return; }
+        },
+        _ => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, "Either an absolute path or a root name is required".into())); return; }
+    };
+    app.fs_scope.allow(&path);
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(path.to_string_lossy())));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadFileParams {
+    #[serde(flatten)]
+    path: PathParams,
+    #[serde(default)]
+    encoding: Encoding,
+}
+
+pub fn op_fs_read_file(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, path)) = scoped::<ReadFileParams>(app, params, &id, true, |p| &p.path) else { return };
+    match std::fs::read(&path) {
+        Ok(bytes) => {
+            let data = match p.encoding {
+                Encoding::Base64 => base64::engine::general_purpose::STANDARD.encode(&bytes),
+                Encoding::Utf8 => match String::from_utf8(bytes) {
+                    Ok(s) => s,
+                    Err(_) => { let _ = app.tx_out.send(RpcResponse::error(id, -33092, "File is not valid UTF-8; read it as base64".into())); return; }
+                },
+            };
+            let _ = app.tx_out.send(RpcResponse::result(id, json!({ "data": data })));
+        }
+        Err(e) => io_error(app, id, e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteFileParams {
+    #[serde(flatten)]
+    path: PathParams,
+    data: String,
+    #[serde(default)]
+    encoding: Encoding,
+    #[serde(default)]
+    append: bool,
+}
+
+pub fn op_fs_write_file(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, path)) = scoped::<WriteFileParams>(app, params, &id, true, |p| &p.path) else { return };
+    let bytes = match p.encoding {
+        Encoding::Utf8 => p.data.into_bytes(),
+        Encoding::Base64 => match base64::engine::general_purpose::STANDARD.decode(p.data.trim()) {
+            Ok(b) => b,
+            Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, format!("Invalid base64 data: {}", e))); return; }
+        },
+    };
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).append(p.append).truncate(!p.append);
+    // resolve() refused a symlink here; don't follow one created since
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.custom_flags(libc::O_NOFOLLOW);
+    }
+    let res = opts.open(&path).and_then(|mut f| f.write_all(&bytes));
+    match res {
+        Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err(e) => io_error(app, id, e),
+    }
+}
+
+pub fn op_fs_read_dir(app: &mut App, params: Value, id: RpcId) {
+    let Some((_, path)) = scoped::<PathParams>(app, params, &id, true, |p| p) else { return };
+    let res = std::fs::read_dir(&path).and_then(|entries| {
+        entries.map(|e| {
+            let e = e?;
+            let t = e.file_type()?;
+            Ok(json!({ "name": e.file_name().to_string_lossy(), "isFile": t.is_file(), "isDirectory": t.is_dir(), "isSymlink": t.is_symlink() }))
+        }).collect::<std::io::Result<Vec<_>>>()
+    });
+    match res {
+        Ok(list) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(list))); }
+        Err(e) => io_error(app, id, e),
+    }
+}
+
+pub fn op_fs_stat(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, path)) = scoped::<PathParams>(app, params, &id, true, |p| p) else { return };
+    // The resolved path has its symlinks followed, so isSymlink comes from the requested one
+    let requested = p.joined(app).unwrap_or_else(|_| path.clone());
+    match std::fs::metadata(&path) {
+        Ok(m) => {
+            let _ = app.tx_out.send(RpcResponse::result(id, json!({
+                "size": m.len(),
+                "isFile": m.is_file(),
+                "isDirectory": m.is_dir(),
+                "isSymlink": std::fs::symlink_metadata(&requested).is_ok_and(|m| m.file_type().is_symlink()),
+                "readonly": m.permissions().readonly(),
+                "modified": millis(m.modified()),
+                "created": millis(m.created()),
+            })));
+        }
+        Err(e) => io_error(app, id, e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecursiveParams {
+    #[serde(flatten)]
+    path: PathParams,
+    #[serde(default)]
+    recursive: bool,
+}
+
+pub fn op_fs_mkdir(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, path)) = scoped::<RecursiveParams>(app, params, &id, true, |p| &p.path) else { return };
+    let res = if p.recursive { std::fs::create_dir_all(&path) } else { std::fs::create_dir(&path) };
+    match res {
+        Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err(e) => io_error(app, id, e),
+    }
+}
+
+pub fn op_fs_remove(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, path)) = scoped::<RecursiveParams>(app, params, &id, false, |p| &p.path) else { return };
+    if app.fs_scope.is_root(&path) { let _ = app.tx_out.send(RpcResponse::error(id, -33090, "An allowed root can't be removed".into())); return; }
+    let res = match std::fs::symlink_metadata(&path) {
+        Ok(m) if m.is_dir() && p.recursive => std::fs::remove_dir_all(&path),
+        Ok(m) if m.is_dir() => std::fs::remove_dir(&path),
+        Ok(_) => std::fs::remove_file(&path),
+        Err(e) => Err(e),
+    };
+    match res {
+        Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err(e) => io_error(app, id, e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchParams {
+    #[serde(flatten)]
+    path: PathParams,
+    #[serde(default)]
+    recursive: bool,
+    // Globs like .gitignore's ("node_modules", "build/*.js"); a match also hides everything below
+    #[serde(default)]
+    ignore: Vec<String>,
+    debounce_ms: Option<u64>,
+}
+
+fn event_kind(kind: &EventKind) -> Option<&'static str> {
+    match kind {
+        EventKind::Create(_) => Some("create"),
+        EventKind::Modify(ModifyKind::Name(_)) => Some("rename"),
+        EventKind::Modify(_) => Some("modify"),
+        EventKind::Remove(_) => Some("remove"),
+        // Reads and opens would flood the stream
+        EventKind::Access(_) => None,
+        EventKind::Any | EventKind::Other => Some("other"),
+    }
+}
+
+/// Ignore patterns of a watch, split as in .gitignore: globs containing a `/` are matched against
+/// the path relative to the watched one, others against every name in it, so `node_modules`
+/// ignores `a/node_modules/x` too.
+struct Ignore { paths: GlobSet, names: GlobSet }
+
+impl Ignore {
+    fn new(patterns: &[String]) -> std::result::Result<Self, globset::Error> {
+        let (mut paths, mut names) = (GlobSetBuilder::new(), GlobSetBuilder::new());
+        for pattern in patterns {
+            let glob = Glob::new(pattern)?;
+            if pattern.contains('/') { paths.add(glob); } else { names.add(glob); }
+        }
+        Ok(Self { paths: paths.build()?, names: names.build()? })
+    }
+}
+
+fn ignored(ignore: &Ignore, root: &Path, path: &Path) -> bool {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components().any(|c| ignore.names.is_match(c.as_os_str()))
+        || rel.ancestors().any(|a| !a.as_os_str().is_empty() && ignore.paths.is_match(a))
+}
+
+// Collect changes until the tree has been quiet for `delay` (but at most 5x `delay` after the
+// first one), then emit them as one fs.changed. Ends when the watcher, and with it `rx`, is dropped.
+fn debounce(rx: Receiver<notify::Event>, watch_id: String, root: PathBuf, ignore: Ignore, delay: Duration, tx_out: Sender<RpcResponse>) {
+    let mut pending: Vec<(&'static str, Vec<String>)> = Vec::new();
+    let (mut first, mut last) = (Instant::now(), Instant::now());
+    loop {
+        let next = if pending.is_empty() { rx.recv().map_err(|_| RecvTimeoutError::Disconnected) } else { rx.recv_deadline((last + delay).min(first + delay * 5)) };
+        let ev = match next {
+            Ok(ev) => ev,
+            Err(RecvTimeoutError::Timeout) => {
+                let events: Vec<Value> = pending.drain(..).map(|(kind, paths)| json!({ "kind": kind, "paths": paths })).collect();
+                let _ = tx_out.send(RpcResponse::notify("fs.changed", json!({ "watchId": watch_id, "events": events })));
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        let Some(kind) = event_kind(&ev.kind) else { continue };
+        let paths: Vec<String> = ev.paths.iter().filter(|p| !ignored(&ignore, &root, p)).map(|p| p.to_string_lossy().to_string()).collect();
+        if paths.is_empty() { continue; }
+        if pending.is_empty() { first = Instant::now(); }
+        last = Instant::now();
+        // Editors often save in several steps that report the same change repeatedly
+        if !pending.iter().any(|(k, ps)| *k == kind && *ps == paths) { pending.push((kind, paths)); }
+    }
+}
+
+pub fn op_fs_watch(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, path)) = scoped::<WatchParams>(app, params, &id, true, |p| &p.path) else { return };
+    let ignore = match Ignore::new(&p.ignore) {
+        Ok(set) => set,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let (tx, rx) = crossbeam_channel::unbounded::<notify::Event>();
+    let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| { if let Ok(ev) = res { let _ = tx.send(ev); } });
+    let mode = if p.recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+    let watcher = watcher.and_then(|mut w| w.watch(&path, mode).map(|_| w));
+    match watcher {
+        Ok(watcher) => {
+            let watch_id = Uuid::new_v4().to_string();
+            let delay = Duration::from_millis(p.debounce_ms.unwrap_or(100).max(10));
+            let (tx_out, wid, root) = (app.tx_out.clone(), watch_id.clone(), path.clone());
+            std::thread::spawn(move || debounce(rx, wid, root, ignore, delay, tx_out));
+            app.fs_watches.insert(watch_id.clone(), watcher);
+            let _ = app.tx_out.send(RpcResponse::result(id, json!({ "watchId": watch_id, "path": path.to_string_lossy() })));
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33093, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UnwatchParams { watch_id: String }
+
+pub fn op_fs_unwatch(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<UnwatchParams>(params) {
+        // Dropping the watcher stops it and ends its debounce thread
+        Ok(p) => { let removed = app.fs_watches.remove(&p.watch_id).is_some(); let _ = app.tx_out.send(RpcResponse::result(id, json!(removed))); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory with its symlinks resolved (the temp dir itself is one on macOS)
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nanoframe-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    fn scope(root: &Path) -> FsScope {
+        let mut scope = FsScope::default();
+        scope.allow(root);
+        scope
+    }
+
+    #[test]
+    fn real_path_appends_the_missing_tail() {
+        let dir = temp_dir();
+        assert_eq!(real_path(&dir.join("a/b.txt")), Some(dir.join("a/b.txt")));
+        std::fs::create_dir(dir.join("a")).unwrap();
+        assert_eq!(real_path(&dir.join("a")), Some(dir.join("a")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn resolves_paths_inside_the_roots() {
+        let dir = temp_dir();
+        let scope = scope(&dir.join("root"));
+        // Roots that don't exist yet still count once created
+        std::fs::create_dir(dir.join("root")).unwrap();
+        assert_eq!(scope.resolve(&dir.join("root"), true), Ok(dir.join("root")));
+        assert_eq!(scope.resolve(&dir.join("root/new/file.txt"), true), Ok(dir.join("root/new/file.txt")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_relative_parent_and_outside_paths() {
+        let dir = temp_dir();
+        std::fs::create_dir(dir.join("root")).unwrap();
+        let scope = scope(&dir.join("root"));
+        assert!(scope.resolve(Path::new("root/file.txt"), true).unwrap_err().contains("must be absolute"));
+        assert!(scope.resolve(&dir.join("root/../secret"), true).unwrap_err().contains("'..'"));
+        assert!(scope.resolve(&dir.join("rootless/file.txt"), true).unwrap_err().contains("outside the allowed roots"));
+        assert!(scope.resolve(&dir, true).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_a_root_are_refused() {
+        use std::os::unix::fs::symlink;
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("root")).unwrap();
+        std::fs::create_dir_all(dir.join("outside")).unwrap();
+        std::fs::write(dir.join("outside/secret"), b"").unwrap();
+        symlink(dir.join("outside"), dir.join("root/dir-link")).unwrap();
+        symlink(dir.join("outside/secret"), dir.join("root/file-link")).unwrap();
+        let scope = scope(&dir.join("root"));
+        assert!(scope.resolve(&dir.join("root/dir-link/secret"), true).is_err());
+        assert!(scope.resolve(&dir.join("root/dir-link/new.txt"), true).is_err());
+        assert!(scope.resolve(&dir.join("root/file-link"), true).is_err());
+        // Without following, the link itself is what gets touched (e.g. removed)
+        assert_eq!(scope.resolve(&dir.join("root/file-link"), false), Ok(dir.join("root/file-link")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_symlinks_are_refused() {
+        use std::os::unix::fs::symlink;
+        let dir = temp_dir();
+        std::fs::create_dir_all(dir.join("root")).unwrap();
+        symlink(dir.join("outside/not-yet"), dir.join("root/dangling")).unwrap();
+        let scope = scope(&dir.join("root"));
+        assert_eq!(real_path(&dir.join("root/dangling")), None);
+        assert!(scope.resolve(&dir.join("root/dangling"), true).unwrap_err().contains("dangling symlink"));
+        assert!(scope.resolve(&dir.join("root/dangling/file.txt"), true).unwrap_err().contains("dangling symlink"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
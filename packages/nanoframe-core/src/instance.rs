@@ -0,0 +1,206 @@
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use crossbeam_channel::Sender;
+#[cfg(unix)]
+use directories::BaseDirs;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tao::event_loop::ControlFlow;
+use uuid::Uuid;
+
+// A client that connects and then says nothing is dropped after this long
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+// How long a later launch keeps looking for the port file of a first instance that just started
+#[cfg(windows)]
+const PORT_FILE_WAIT: Duration = Duration::from_secs(1);
+
+/// Held by the first instance for the lifetime of the process. On Unix it is a socket in the
+/// runtime dir or a private per-user dir; on Windows an exclusively opened lock file plus a
+/// loopback port for forwarding.
+pub struct InstanceLock {
+    path: PathBuf,
+    #[cfg(windows)]
+    _lock: std::fs::File,
+}
+
+impl InstanceLock {
+    /// Remove the socket / port file so the next launch doesn't have to detect a stale one.
+    pub fn release(self) {
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.path);
+        #[cfg(windows)]
+        let _ = std::fs::remove_file(self.path.with_extension("port"));
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockParams {
+    app_name: Option<String>,
+    // Node's entry script (or executable), naming the lock when there is no app identity
+    entry: Option<String>,
+    // Forwarded to the first instance if this one isn't it
+    #[serde(default)]
+    argv: Vec<String>,
+    cwd: Option<String>,
+}
+
+// Without $XDG_RUNTIME_DIR the lock goes into a private per-user directory under the temp dir, so
+// another user's instance can neither take the lock nor receive this user's launches
+#[cfg(unix)]
+fn lock_dir() -> std::io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    if let Some(dir) = BaseDirs::new().and_then(|b| b.runtime_dir().map(Path::to_path_buf)) { return Ok(dir); }
+    let uid = unsafe { libc::getuid() };
+    let dir = std::env::temp_dir().join(format!("nanoframe-{}", uid));
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e),
+    }
+    // Someone else may have created it first
+    let meta = std::fs::symlink_metadata(&dir)?;
+    if !meta.is_dir() || meta.uid() != uid || meta.permissions().mode() & 0o077 != 0 {
+        return Err(std::io::Error::other(format!("{} is not a private directory owned by this user", dir.display())));
+    }
+    Ok(dir)
+}
+
+// %TEMP% is per-user on Windows, and forwarded launches must carry the port file's token anyway
+#[cfg(windows)]
+fn lock_dir() -> std::io::Result<PathBuf> {
+    Ok(std::env::temp_dir())
+}
+
+// One lock per app identity
+fn lock_path(app_name: &str) -> std::io::Result<PathBuf> {
+    let name: String = app_name.chars().map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' }).collect();
+    Ok(lock_dir()?.join(format!("{}.instance", name)))
+}
+
+// Read one forwarded launch and hand it to Node
+fn forward_to_node(stream: impl Read, tx_out: &Sender<RpcResponse>, token: Option<&str>) {
+    let mut line = String::new();
+    // Bounded by the read timeout set on the stream
+    if BufReader::new(stream.take(64 * 1024)).read_line(&mut line).is_err() { return; }
+    let Ok(msg) = serde_json::from_str::<Value>(&line) else { return };
+    if token.is_some_and(|t| msg.get("token").and_then(|v| v.as_str()) != Some(t)) { return; }
+    let _ = tx_out.send(RpcResponse::notify("app.secondInstance", json!({ "argv": msg.get("argv"), "cwd": msg.get("cwd") })));
+}
+
+/// Take the lock, or forward `launch` to the instance holding it (`Ok(None)`).
+#[cfg(unix)]
+fn acquire(path: &Path, launch: &Value, tx_out: Sender<RpcResponse>) -> std::io::Result<Option<InstanceLock>> {
+    use std::os::fd::AsRawFd;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    // Launches racing each other take turns, so one can't unlink a socket the other just bound as stale.
+    // The lock file stays behind: removing it would let a waiting launch lock a different file
+    let guard = std::fs::OpenOptions::new().write(true).create(true).truncate(false).open(path.with_extension("lock"))?;
+    if unsafe { libc::flock(guard.as_raw_fd(), libc::LOCK_EX) } != 0 { return Err(std::io::Error::last_os_error()); }
+    let listener = match UnixListener::bind(path) {
+        Ok(l) => l,
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => {
+            if let Ok(mut stream) = UnixStream::connect(path) {
+                writeln!(stream, "{}", launch)?;
+                return Ok(None);
+            }
+            // Nobody is listening: a previous instance died without cleaning up
+            std::fs::remove_file(path)?;
+            UnixListener::bind(path)?
+        }
+        Err(e) => return Err(e),
+    };
+    std::thread::spawn(move || {
+        // Each launch on its own thread, so a stalled client can't hold up the next one
+        for stream in listener.incoming().flatten() {
+            let tx_out = tx_out.clone();
+            let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+            std::thread::spawn(move || forward_to_node(stream, &tx_out, None));
+        }
+    });
+    Ok(Some(InstanceLock { path: path.to_path_buf() }))
+}
+
+#[cfg(windows)]
+fn acquire(path: &Path, launch: &Value, tx_out: Sender<RpcResponse>) -> std::io::Result<Option<InstanceLock>> {
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::os::windows::fs::OpenOptionsExt;
+    let port_file = path.with_extension("port");
+    // No sharing: while the first instance runs, opening the lock file again fails
+    match std::fs::OpenOptions::new().write(true).create(true).truncate(true).share_mode(0).open(path) {
+        Ok(lock) => {
+            // A port file left by an instance that crashed would send later launches nowhere
+            let _ = std::fs::remove_file(&port_file);
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+            // Any local process can reach the port, so launches must carry the token from the per-user port file
+            let token = Uuid::new_v4().to_string();
+            // Written aside and renamed into place, so a later launch never reads it half-written
+            let tmp = port_file.with_extension("port.tmp");
+            std::fs::write(&tmp, format!("{} {}", listener.local_addr()?.port(), token))?;
+            std::fs::rename(&tmp, &port_file)?;
+            std::thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let (tx_out, token) = (tx_out.clone(), token.clone());
+                    let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+                    std::thread::spawn(move || forward_to_node(stream, &tx_out, Some(&token)));
+                }
+            });
+            Ok(Some(InstanceLock { path: path.to_path_buf(), _lock: lock }))
+        }
+        Err(_) => {
+            // The first instance may have taken the lock but not written its port file yet
+            let deadline = std::time::Instant::now() + PORT_FILE_WAIT;
+            let (mut stream, token) = loop {
+                match connect_first(&port_file) {
+                    Ok(found) => break found,
+                    Err(e) if std::time::Instant::now() >= deadline => return Err(e),
+                    Err(_) => std::thread::sleep(Duration::from_millis(50)),
+                }
+            };
+            let mut msg = launch.clone();
+            msg["token"] = json!(token);
+            writeln!(stream, "{}", msg)?;
+            Ok(None)
+        }
+    }
+}
+
+// Connect to the first instance's forwarding port; returns the stream and the token to send
+#[cfg(windows)]
+fn connect_first(port_file: &Path) -> std::io::Result<(std::net::TcpStream, String)> {
+    use std::net::{Ipv4Addr, TcpStream};
+    let contents = std::fs::read_to_string(port_file)?;
+    let (port, token) = contents.split_once(' ').ok_or_else(|| std::io::Error::other("Malformed instance port file"))?;
+    let port: u16 = port.parse().map_err(std::io::Error::other)?;
+    Ok((TcpStream::connect((Ipv4Addr::LOCALHOST, port))?, token.to_string()))
+}
+
+pub fn op_request_single_instance_lock(app: &mut App, params: Value, id: RpcId, control_flow: &mut ControlFlow) {
+    let p = match serde_json::from_value::<LockParams>(if params.is_null() { json!({}) } else { params }) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    if app.instance_lock.is_some() { let _ = app.tx_out.send(RpcResponse::result(id, json!({ "acquired": true }))); return; }
+    // Unrelated apps must not share a lock, so without an identity the entry script names it
+    let name = match (app.identity(p.app_name.as_deref()), p.entry.as_deref()) {
+        (Some(name), _) => name.to_string(),
+        (None, Some(entry)) => format!("nanoframe-{}", Uuid::new_v5(&Uuid::NAMESPACE_URL, entry.as_bytes()).simple()),
+        (None, None) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, "appName is required unless app.setName was called".into())); return; }
+    };
+    let launch = json!({ "argv": p.argv, "cwd": p.cwd });
+    match lock_path(&name).and_then(|path| acquire(&path, &launch, app.tx_out.clone())) {
+        Ok(Some(lock)) => {
+            app.instance_lock = Some(lock);
+            let _ = app.tx_out.send(RpcResponse::result(id, json!({ "acquired": true })));
+        }
+        // The first instance has our launch now; this one is done once the reply is written (see LoopDestroyed)
+        Ok(None) => {
+            let _ = app.tx_out.send(RpcResponse::result(id, json!({ "acquired": false })));
+            *control_flow = ControlFlow::Exit;
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33120, e.to_string())); }
+    }
+}
@@ -3,9 +3,22 @@ mod state;
 mod window_ops;
 mod dialogs;
 mod system;
+mod clipboard;
+mod notifications;
+mod shortcuts;
+mod screen;
+mod window_state;
+mod bridge;
+mod profiles;
+mod cookies;
+mod downloads;
+mod fs;
+mod store;
+mod secrets;
+mod instance;
 
 use crate::rpc::{RpcId, RpcResponse};
-use crate::state::App;
+use crate::state::{App, Internal};
 use crate::window_ops::handle_window_event;
 use serde_json::{json, Value};
 use tao::event::Event;
@@ -22,7 +35,10 @@ fn main() {
         *control_flow = ControlFlow::Poll;
 
         // Process incoming commands non-blocking
-        while let Ok(req) = app.rx_cmd.try_recv() {
+        while let Ok(mut req) = app.rx_cmd.try_recv() {
+            window_ops::resolve_labels(&app, &mut req.params);
+            // Requests from pages only run when the window's capabilities allow the method
+            if !bridge::check_permission(&app, &req) { continue; }
             let id = RpcId::from_value(req.id.clone().unwrap_or(Value::Null));
             let tx_out = app.tx_out.clone();
             let send_err = |code: i32, msg: String| {
@@ -70,25 +86,114 @@ fn main() {
                 "window.setDecorations" => window_ops::op_set_decorations(&mut app, req.params, id),
                 "window.setPosition" => window_ops::op_set_position(&mut app, req.params, id),
                 "window.getPosition" => window_ops::op_get_position(&mut app, req.params, id),
+                "window.list" => window_ops::op_list(&mut app, req.params, id),
+                "window.getFocused" => window_ops::op_get_focused(&mut app, req.params, id),
+                "window.getChildren" => window_ops::op_get_children(&mut app, req.params, id),
+                "window.getParent" => window_ops::op_get_parent(&mut app, req.params, id),
+                "window.setIgnoreCursorEvents" => window_ops::op_set_ignore_cursor_events(&mut app, req.params, id),
+                "window.startDrag" => window_ops::op_start_drag(&mut app, req.params, id),
+                "window.startResize" => window_ops::op_start_resize(&mut app, req.params, id),
                 // Webview extras
                 "webview.openDevtools" => window_ops::op_open_devtools(&mut app, req.params, id),
                 "webview.postMessage" => window_ops::op_post_message(&mut app, req.params, id),
+                "webview.setIpcAllowedOrigins" => bridge::op_set_ipc_allowed_origins(&mut app, req.params, id),
+                "webview.setDragDrop" => bridge::op_set_drag_drop(&mut app, req.params, id),
+                "webview.setZoom" => window_ops::op_set_zoom(&mut app, req.params, id),
                 "webview.screenshot" => window_ops::op_screenshot(&mut app, req.params, id),
+                "session.clearData" => profiles::op_session_clear_data(&mut app, req.params, id),
+                "cookies.get" => cookies::op_cookies_get(&mut app, req.params, id),
+                "cookies.getAll" => cookies::op_cookies_get_all(&mut app, req.params, id),
+                "cookies.set" => cookies::op_cookies_set(&mut app, req.params, id),
+                "cookies.delete" => cookies::op_cookies_delete(&mut app, req.params, id),
+                "download.respond" => downloads::op_download_respond(&mut app, req.params, id),
                 // Dialogs + app paths
                 "dialog.open" => dialogs::op_open_dialog(&mut app, req.params, id),
                 "dialog.save" => dialogs::op_save_dialog(&mut app, req.params, id),
                 "app.getPath" => dialogs::op_app_get_path(&mut app, req.params, id),
+                "app.setName" => dialogs::op_app_set_name(&mut app, req.params, id),
+                "app.requestSingleInstanceLock" => instance::op_request_single_instance_lock(&mut app, req.params, id, control_flow),
                 // System helpers
                 "shell.openExternal" => system::op_shell_open(&mut app, req.params, id),
+                "shell.setAllowedSchemes" => system::op_shell_set_allowed_schemes(&mut app, req.params, id),
+                "shell.openPath" => system::op_shell_open_path(&mut app, req.params, id),
+                "shell.showItemInFolder" => system::op_shell_show_item_in_folder(&mut app, req.params, id),
+                "shell.openWith" => system::op_shell_open_with(&mut app, req.params, id),
+                "shell.trashItem" => system::op_shell_trash_item(&mut app, req.params, id),
+                "shell.beep" => system::op_shell_beep(&mut app, req.params, id),
+                // Scoped file system
+                "fs.allow" => fs::op_fs_allow(&mut app, req.params, id),
+                "fs.readFile" => fs::op_fs_read_file(&mut app, req.params, id),
+                "fs.writeFile" => fs::op_fs_write_file(&mut app, req.params, id),
+                "fs.readDir" => fs::op_fs_read_dir(&mut app, req.params, id),
+                "fs.stat" => fs::op_fs_stat(&mut app, req.params, id),
+                "fs.mkdir" => fs::op_fs_mkdir(&mut app, req.params, id),
+                "fs.remove" => fs::op_fs_remove(&mut app, req.params, id),
+                "fs.watch" => fs::op_fs_watch(&mut app, req.params, id),
+                "fs.unwatch" => fs::op_fs_unwatch(&mut app, req.params, id),
+                // Settings stores
+                "store.configure" => store::op_store_configure(&mut app, req.params, id),
+                "store.get" => store::op_store_get(&mut app, req.params, id),
+                "store.set" => store::op_store_set(&mut app, req.params, id),
+                "store.delete" => store::op_store_delete(&mut app, req.params, id),
+                "store.entries" => store::op_store_entries(&mut app, req.params, id),
+                // OS keyring
+                "secrets.set" => secrets::op_secrets_set(&mut app, req.params, id),
+                "secrets.get" => secrets::op_secrets_get(&mut app, req.params, id),
+                "secrets.delete" => secrets::op_secrets_delete(&mut app, req.params, id),
                 "clipboard.writeText" => system::op_clipboard_write(&mut app, req.params, id),
                 "clipboard.readText" => system::op_clipboard_read(&mut app, req.params, id),
+                "clipboard.writeHtml" => system::op_clipboard_write_html(&mut app, req.params, id),
+                "clipboard.readHtml" => system::op_clipboard_read_html(&mut app, req.params, id),
+                "clipboard.writeImage" => system::op_clipboard_write_image(&mut app, req.params, id),
+                "clipboard.readImage" => system::op_clipboard_read_image(&mut app, req.params, id),
+                "clipboard.readFiles" => system::op_clipboard_read_files(&mut app, req.params, id),
+                "clipboard.clear" => system::op_clipboard_clear(&mut app, req.params, id),
+                "clipboard.startWatching" => system::op_clipboard_start_watching(&mut app, req.params, id),
+                "clipboard.stopWatching" => system::op_clipboard_stop_watching(&mut app, req.params, id),
+                "clipboard.setHandoffOnExit" => system::op_clipboard_set_handoff_on_exit(&mut app, req.params, id),
+                // Notifications
+                "notification.show" => notifications::op_notification_show(&mut app, req.params, id),
+                // Global shortcuts
+                "shortcut.register" => shortcuts::op_shortcut_register(&mut app, req.params, id),
+                "shortcut.unregister" => shortcuts::op_shortcut_unregister(&mut app, req.params, id),
+                "shortcut.unregisterAll" => shortcuts::op_shortcut_unregister_all(&mut app, req.params, id),
+                // Displays
+                "screen.getAllDisplays" => screen::op_get_all_displays(target, &mut app, req.params, id),
+                "screen.getPrimaryDisplay" => screen::op_get_primary_display(target, &mut app, req.params, id),
+                "screen.getDisplayNearestPoint" => screen::op_get_display_nearest_point(target, &mut app, req.params, id),
+                "screen.getCursorPosition" => screen::op_get_cursor_position(target, &mut app, req.params, id),
                 _ => send_err(-32601, "Method not found".to_string()),
             }
         }
 
+        // Messages from injected page scripts (drag regions etc.) and webview callbacks
+        while let Ok(msg) = app.rx_internal.try_recv() {
+            match msg {
+                Internal::Bridge { window_id, payload, origin } => bridge::handle_message(&mut app, &window_id, &payload, &origin),
+                Internal::DragDrop { window_id, event } => bridge::handle_drag_drop(&mut app, &window_id, event),
+                Internal::PageReply { window_id, call_id, ok, value } => bridge::resolve_page_call(&app, &window_id, &call_id, ok, &value),
+                Internal::DownloadDialog { download_id } => downloads::show_dialog(&mut app, download_id),
+            }
+        }
+
+        // Global hotkeys fire regardless of focus; forward them as notifications
+        shortcuts::drain_events(&mut app);
+        screen::poll_display_changes(target, &mut app);
+        downloads::expire(&app);
+
         match &event {
             Event::WindowEvent { event, window_id, .. } => handle_window_event(event, *window_id, control_flow, &mut app),
             Event::MainEventsCleared => {}
+            Event::LoopDestroyed => {
+                // Windows still open at exit keep their placement too
+                let ids: Vec<String> = app.window_states.keys().cloned().collect();
+                for wid in ids { window_ops::save_window_state(&mut app, &wid); }
+                // Release the clipboard owner so its selections can be handed off before exit
+                app.clipboard.shutdown();
+                if let Some(lock) = app.instance_lock.take() { lock.release(); }
+                // Replies queued just before exiting (e.g. a lost single-instance lock) must reach Node
+                app.flush_output();
+            }
             _ => {}
         }
     });
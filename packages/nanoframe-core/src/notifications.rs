@@ -0,0 +1,109 @@
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use crate::window_ops::load_rgba;
+use anyhow::Result;
+use notify_rust::{Notification, NotificationHandle, Timeout};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum UrgencyParam { Low, Normal, Critical }
+
+#[derive(Debug, Deserialize)]
+struct NotificationAction { id: String, label: String }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShowNotificationParams {
+    title: String,
+    body: Option<String>,
+    icon_path: Option<String>,
+    urgency: Option<UrgencyParam>,
+    // Milliseconds; 0 keeps the notification until dismissed, omitted uses the server default
+    timeout_ms: Option<u32>,
+    #[serde(default)]
+    actions: Vec<NotificationAction>,
+    app_name: Option<String>,
+}
+
+fn build_notification(p: &ShowNotificationParams) -> Result<Notification> {
+    let mut n = Notification::new();
+    n.summary(&p.title);
+    if let Some(body) = p.body.as_deref() { n.body(body); }
+    if let Some(name) = p.app_name.as_deref() { n.appname(name); }
+    if let Some(path) = p.icon_path.as_deref() {
+        // Decode with the same loader as window icons so png/ico behave identically
+        #[cfg(all(unix, not(target_os = "macos")))]
+        { n.image_data(notify_rust::Image::try_from(load_rgba(path)?)?); }
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        { load_rgba(path)?; n.image_path(path); }
+    }
+    // macOS has no urgency levels without the UNUserNotifications backend
+    #[cfg(target_os = "macos")]
+    let _ = p.urgency;
+    #[cfg(not(target_os = "macos"))]
+    if let Some(u) = p.urgency {
+        n.urgency(match u { UrgencyParam::Low => notify_rust::Urgency::Low, UrgencyParam::Normal => notify_rust::Urgency::Normal, UrgencyParam::Critical => notify_rust::Urgency::Critical });
+    }
+    if let Some(ms) = p.timeout_ms { n.timeout(if ms == 0 { Timeout::Never } else { Timeout::Milliseconds(ms) }); }
+    for a in &p.actions { n.action(&a.id, &a.label); }
+    Ok(n)
+}
+
+// How long a listener waits for a response. Notifications shown with `timeoutMs: 0`, or servers that
+// never report `closed`, would otherwise keep a thread parked for the rest of the process.
+#[cfg(target_os = "linux")]
+const LISTEN_LIMIT: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+#[cfg(target_os = "linux")]
+fn wait_for_action(handle: NotificationHandle, on_action: impl FnOnce(&str)) {
+    use notify_rust::NotificationResponse;
+    let wait = handle.wait_for_action_async(|response| match response {
+        NotificationResponse::Default => on_action("default"),
+        NotificationResponse::Action(action) => on_action(action),
+        NotificationResponse::Closed(_) => on_action("__closed"),
+        _ => {}
+    });
+    let limit = async { async_io::Timer::after(LISTEN_LIMIT).await; };
+    zbus::block_on(futures_lite::future::or(wait, limit));
+}
+
+// Windows toasts and macOS banners always leave the screen on their own and report it, which ends the wait
+#[cfg(not(target_os = "linux"))]
+fn wait_for_action(handle: NotificationHandle, on_action: impl FnOnce(&str)) {
+    handle.wait_for_action(on_action);
+}
+
+pub fn op_notification_show(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<ShowNotificationParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let n = match build_notification(&p) {
+        Ok(n) => n,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33021, e.to_string())); return; }
+    };
+    let handle = match n.show() {
+        Ok(h) => h,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33020, e.to_string())); return; }
+    };
+    let notification_id = Uuid::new_v4().to_string();
+    // Waiting for the user's response blocks, so each notification gets its own listener thread
+    {
+        let tx = app.tx_out.clone();
+        let nid = notification_id.clone();
+        std::thread::spawn(move || {
+            wait_for_action(handle, |action| {
+                let msg = match action {
+                    "default" => RpcResponse::notify("notification.clicked", json!({ "notificationId": nid })),
+                    "__closed" => RpcResponse::notify("notification.closed", json!({ "notificationId": nid })),
+                    other => RpcResponse::notify("notification.action", json!({ "notificationId": nid, "actionId": other })),
+                };
+                let _ = tx.send(msg);
+            });
+        });
+    }
+    let _ = app.tx_out.send(RpcResponse::result(id, json!({ "notificationId": notification_id })));
+}
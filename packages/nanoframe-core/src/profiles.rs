@@ -0,0 +1,120 @@
+use crate::dialogs::{app_path, DEFAULT_APP_NAME};
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use wry::WebContext;
+
+/// Named browser profiles: each one is a `WebContext` whose cookies, storage and cache live in
+/// `<userData>/profiles/<name>`, so windows on different profiles don't share a session. Profiles
+/// belong to an app identity: two identities' "main" profiles are separate sessions.
+#[derive(Default)]
+pub struct ProfileRegistry {
+    // (app identity, profile name) -> (data directory, context); contexts live as long as the process
+    contexts: HashMap<ProfileKey, (PathBuf, WebContext)>,
+    // window id -> (app identity, profile name)
+    windows: HashMap<String, ProfileKey>,
+}
+
+type ProfileKey = (String, String);
+
+fn key(name: &str, app_name: Option<&str>) -> ProfileKey {
+    (app_name.unwrap_or(DEFAULT_APP_NAME).to_string(), name.to_string())
+}
+
+// Profile names become directory names, so keep them to a safe character set
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    let ok = !name.is_empty() && name != "." && name != ".." && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if ok { Ok(()) } else { Err(anyhow!("Invalid profile name '{}': use letters, digits, '-', '_' or '.'", name)) }
+}
+
+fn data_dir(name: &str, app_name: Option<&str>) -> Result<PathBuf> {
+    Ok(app_path("userData", app_name).ok_or(anyhow!("userData directory unavailable"))?.join("profiles").join(name))
+}
+
+/// WKWebView has no data directory; macOS 14+ separates sessions by a data store identifier instead.
+#[cfg(target_os = "macos")]
+pub fn data_store_id(name: &str, app_name: Option<&str>) -> [u8; 16] {
+    let (app_name, name) = key(name, app_name);
+    uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_OID, format!("{}/{}", app_name, name).as_bytes()).into_bytes()
+}
+
+impl ProfileRegistry {
+    /// Context for `name`, created (with its data directory) on first use.
+    pub fn context(&mut self, name: &str, app_name: Option<&str>) -> Result<&mut WebContext> {
+        validate_name(name)?;
+        let entry = match self.contexts.entry(key(name, app_name)) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(v) => {
+                let dir = data_dir(name, app_name)?;
+                std::fs::create_dir_all(&dir)?;
+                let ctx = WebContext::new(Some(dir.clone()));
+                v.insert((dir, ctx))
+            }
+        };
+        Ok(&mut entry.1)
+    }
+
+    pub fn attach(&mut self, window_id: &str, name: &str, app_name: Option<&str>) { self.windows.insert(window_id.to_string(), key(name, app_name)); }
+
+    pub fn detach(&mut self, window_id: &str) { self.windows.remove(window_id); }
+
+    /// Any open window using profile `name` of `app_name`.
+    pub fn window_for(&self, name: &str, app_name: Option<&str>) -> Option<&str> {
+        let key = key(name, app_name);
+        self.windows.iter().find(|(_, k)| **k == key).map(|(w, _)| w.as_str())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClearDataParams { profile: String, app_name: Option<String> }
+
+pub fn op_session_clear_data(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<ClearDataParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    if let Err(e) = validate_name(&p.profile) { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    let app_name = app.identity(p.app_name.as_deref()).map(str::to_string);
+    let profile = key(&p.profile, app_name.as_deref());
+    let open: Vec<&String> = app.profiles.windows.iter().filter(|(_, k)| **k == profile).map(|(w, _)| w).collect();
+    // While windows use the profile its files are locked/in use; clear through the webview instead
+    if !open.is_empty() {
+        for wid in open {
+            if let Some(Err(e)) = app.webviews.get(wid).map(|wv| wv.clear_all_browsing_data()) {
+                let _ = app.tx_out.send(RpcResponse::error(id, -33050, e.to_string()));
+                return;
+            }
+        }
+        let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+        return;
+    }
+    let dir = match app.profiles.contexts.remove(&profile) {
+        Some((dir, _ctx)) => dir,
+        None => match data_dir(&p.profile, app_name.as_deref()) {
+            Ok(dir) => dir,
+            Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33050, e.to_string())); return; }
+        },
+    };
+    #[cfg(target_os = "macos")]
+    {
+        use wry::WebViewExtDarwin;
+        let tx = app.tx_out.clone();
+        wry::WebView::remove_data_store(&data_store_id(&p.profile, app_name.as_deref()), move |res| {
+            let msg = match res { Ok(()) => RpcResponse::result(id, json!(true)), Err(e) => RpcResponse::error(id, -33050, e.to_string()) };
+            let _ = tx.send(msg);
+        });
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    #[cfg(not(target_os = "macos"))]
+    match std::fs::remove_dir_all(&dir) {
+        Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33050, e.to_string())); }
+    }
+}
@@ -9,6 +9,9 @@ pub struct RpcRequest {
     pub method: String,
     #[serde(default)]
     pub params: Value,
+    // Window whose page made the call through the IPC bridge; never set from Node's input
+    #[serde(skip)]
+    pub caller: Option<String>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -0,0 +1,139 @@
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tao::event_loop::EventLoopWindowTarget;
+use tao::monitor::MonitorHandle;
+
+// tao has no display-change event on every platform, so the layout is re-read at this interval
+const DISPLAY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Last seen display layout, used to emit `screen.displaysChanged`.
+pub struct ScreenWatch {
+    last: Option<Vec<Value>>,
+    checked_at: Instant,
+}
+
+impl Default for ScreenWatch {
+    fn default() -> Self { Self { last: None, checked_at: Instant::now() } }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Rect { pub x: i32, pub y: i32, pub width: u32, pub height: u32 }
+
+impl Rect {
+    fn to_json(self) -> Value { json!({ "x": self.x, "y": self.y, "width": self.width, "height": self.height }) }
+
+    // Overlapping area with another rectangle, in square pixels
+    pub(crate) fn intersection_area(self, o: Rect) -> i64 {
+        let w = (self.x + self.width as i32).min(o.x + o.width as i32) - self.x.max(o.x);
+        let h = (self.y + self.height as i32).min(o.y + o.height as i32) - self.y.max(o.y);
+        if w <= 0 || h <= 0 { 0 } else { w as i64 * h as i64 }
+    }
+
+    // Squared distance from a point to the rectangle (0 when inside)
+    fn distance_sq(self, px: f64, py: f64) -> f64 {
+        let dx = (self.x as f64 - px).max(0.0).max(px - (self.x as f64 + self.width as f64));
+        let dy = (self.y as f64 - py).max(0.0).max(py - (self.y as f64 + self.height as f64));
+        dx * dx + dy * dy
+    }
+}
+
+pub(crate) fn monitor_bounds(m: &MonitorHandle) -> Rect {
+    let pos = m.position();
+    let size = m.size();
+    Rect { x: pos.x, y: pos.y, width: size.width, height: size.height }
+}
+
+// Area not covered by panels/taskbars/docks, in physical pixels
+#[cfg(target_os = "linux")]
+pub(crate) fn monitor_work_area(m: &MonitorHandle) -> Rect {
+    use gtk::gdk::prelude::MonitorExt;
+    use tao::platform::unix::MonitorHandleExtUnix;
+    // GDK reports application pixels; scale them to match the physical bounds
+    let r = m.gdk_monitor().workarea();
+    let s = m.scale_factor();
+    Rect { x: (r.x() as f64 * s) as i32, y: (r.y() as f64 * s) as i32, width: (r.width() as f64 * s) as u32, height: (r.height() as f64 * s) as u32 }
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn monitor_work_area(m: &MonitorHandle) -> Rect {
+    use tao::platform::windows::MonitorHandleExtWindows;
+    use windows_sys::Win32::Graphics::Gdi::{GetMonitorInfoW, MONITORINFO};
+    let mut info: MONITORINFO = unsafe { std::mem::zeroed() };
+    info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+    // SAFETY: hmonitor comes from a live MonitorHandle and info.cbSize is initialised
+    if unsafe { GetMonitorInfoW(m.hmonitor() as _, &mut info) } == 0 { return monitor_bounds(m); }
+    let r = info.rcWork;
+    Rect { x: r.left, y: r.top, width: (r.right - r.left) as u32, height: (r.bottom - r.top) as u32 }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub(crate) fn monitor_work_area(m: &MonitorHandle) -> Rect { monitor_bounds(m) }
+
+fn display_json(m: &MonitorHandle, primary: Option<&MonitorHandle>) -> Value {
+    // Highest refresh rate the monitor advertises, in Hz
+    let refresh_rate = m.video_modes().map(|v| v.refresh_rate()).max();
+    json!({
+        "name": m.name(),
+        "bounds": monitor_bounds(m).to_json(),
+        "workArea": monitor_work_area(m).to_json(),
+        "scaleFactor": m.scale_factor(),
+        "refreshRate": refresh_rate,
+        "primary": primary.is_some_and(|p| p == m),
+    })
+}
+
+fn all_displays(target: &EventLoopWindowTarget<()>) -> Vec<Value> {
+    let primary = target.primary_monitor();
+    target.available_monitors().map(|m| display_json(&m, primary.as_ref())).collect()
+}
+
+pub fn op_get_all_displays(target: &EventLoopWindowTarget<()>, app: &mut App, _params: Value, id: RpcId) {
+    let _ = app.tx_out.send(RpcResponse::result(id, json!({ "displays": all_displays(target) })));
+}
+
+pub fn op_get_primary_display(target: &EventLoopWindowTarget<()>, app: &mut App, _params: Value, id: RpcId) {
+    // Some platforms (e.g. Wayland) can't tell which monitor is primary; fall back to the first one
+    match target.primary_monitor().or_else(|| target.available_monitors().next()) {
+        Some(m) => { let _ = app.tx_out.send(RpcResponse::result(id, display_json(&m, target.primary_monitor().as_ref()))); }
+        None => { let _ = app.tx_out.send(RpcResponse::error(id, -33040, "No display available".into())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PointParams { x: f64, y: f64 }
+
+pub fn op_get_display_nearest_point(target: &EventLoopWindowTarget<()>, app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<PointParams>(params) {
+        Ok(p) => {
+            let nearest = target.monitor_from_point(p.x, p.y).or_else(|| {
+                target.available_monitors().min_by(|a, b| monitor_bounds(a).distance_sq(p.x, p.y).total_cmp(&monitor_bounds(b).distance_sq(p.x, p.y)))
+            });
+            match nearest {
+                Some(m) => { let _ = app.tx_out.send(RpcResponse::result(id, display_json(&m, target.primary_monitor().as_ref()))); }
+                None => { let _ = app.tx_out.send(RpcResponse::error(id, -33040, "No display available".into())); }
+            }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_get_cursor_position(target: &EventLoopWindowTarget<()>, app: &mut App, _params: Value, id: RpcId) {
+    match target.cursor_position() {
+        Ok(pos) => { let _ = app.tx_out.send(RpcResponse::result(id, json!({ "x": pos.x, "y": pos.y }))); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33041, e.to_string())); }
+    }
+}
+
+/// Emit `screen.displaysChanged` when monitors are added, removed, moved or rescaled.
+pub fn poll_display_changes(target: &EventLoopWindowTarget<()>, app: &mut App) {
+    if app.screen.checked_at.elapsed() < DISPLAY_POLL_INTERVAL { return; }
+    app.screen.checked_at = Instant::now();
+    let displays = all_displays(target);
+    if app.screen.last.as_ref().is_some_and(|last| *last != displays) {
+        let _ = app.tx_out.send(RpcResponse::notify("screen.displaysChanged", json!({ "displays": displays })));
+    }
+    app.screen.last = Some(displays);
+}
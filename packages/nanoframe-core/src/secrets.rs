@@ -0,0 +1,228 @@
+use crate::dialogs::{app_path, DEFAULT_APP_NAME};
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use anyhow::{anyhow, Result};
+use base64::Engine as _;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::path::PathBuf;
+
+// Opt-in for the encrypted file used when no OS keyring is reachable (headless CI, containers)
+const FILE_KEY_ENV: &str = "NANOFRAME_SECRETS_KEY";
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+/// Where secrets live: the OS keyring (Secret Service, Keychain, Credential Manager), or the
+/// fallback file when the keyring is unavailable and `NANOFRAME_SECRETS_KEY` is set.
+enum Backend { Keyring(keyring::Entry), File(SecretFile) }
+
+// Keyring entries are (service = app identity, user = key), the same identity app.getPath uses
+fn backend(app_name: Option<&str>, key: &str) -> Result<Backend> {
+    let service = app_name.unwrap_or(DEFAULT_APP_NAME);
+    let entry = keyring::Entry::new(service, key);
+    // Probing with a read is the only way to learn whether the keyring service is there
+    let unavailable = match &entry {
+        Ok(e) => matches!(e.get_password(), Err(keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_))),
+        Err(keyring::Error::PlatformFailure(_) | keyring::Error::NoStorageAccess(_)) => true,
+        Err(_) => false,
+    };
+    match std::env::var(FILE_KEY_ENV) {
+        Ok(pass) if unavailable && !pass.is_empty() => Ok(Backend::File(SecretFile::open(app_name, &pass)?)),
+        _ => Ok(Backend::Keyring(entry?)),
+    }
+}
+
+/// On-disk layout of the fallback file: the salt the file key is derived with, and
+/// key -> base64(nonce || ChaCha20-Poly1305 ciphertext).
+#[derive(Serialize, Deserialize)]
+struct FileData { salt: String, entries: Map<String, Value> }
+
+/// `<appData>/secrets.json`, encrypted under a key derived from `NANOFRAME_SECRETS_KEY` with Argon2id
+/// and a per-file random salt. The key name is authenticated data, so a value can't be moved to
+/// another key without failing to decrypt.
+struct SecretFile { path: PathBuf, cipher: ChaCha20Poly1305, data: FileData }
+
+impl SecretFile {
+    fn open(app_name: Option<&str>, passphrase: &str) -> Result<Self> {
+        let path = app_path("appData", app_name).ok_or(anyhow!("appData directory unavailable"))?.join("secrets.json");
+        Self::at(path, passphrase)
+    }
+
+    fn at(path: PathBuf, passphrase: &str) -> Result<Self> {
+        let data = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| anyhow!("Unreadable {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                FileData { salt: base64::engine::general_purpose::STANDARD.encode(salt), entries: Map::new() }
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let salt = base64::engine::general_purpose::STANDARD.decode(&data.salt)?;
+        let mut key = [0u8; 32];
+        argon2::Argon2::default().hash_password_into(passphrase.as_bytes(), &salt, &mut key).map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+        let cipher = ChaCha20Poly1305::new_from_slice(&key).map_err(|e| anyhow!(e.to_string()))?;
+        Ok(Self { path, cipher, data })
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        let Some(stored) = self.data.entries.get(key).and_then(|v| v.as_str()) else { return Ok(None) };
+        let bytes = base64::engine::general_purpose::STANDARD.decode(stored)?;
+        if bytes.len() < NONCE_LEN { return Err(anyhow!("Corrupt secret '{}'", key)); }
+        let (nonce, msg) = bytes.split_at(NONCE_LEN);
+        let plain = self.cipher.decrypt(Nonce::from_slice(nonce), Payload { msg, aad: key.as_bytes() }).map_err(|_| anyhow!("Can't decrypt '{}': wrong {} or corrupt file", key, FILE_KEY_ENV))?;
+        Ok(Some(String::from_utf8(plain)?))
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut bytes = nonce.to_vec();
+        bytes.extend(self.cipher.encrypt(&nonce, Payload { msg: value.as_bytes(), aad: key.as_bytes() }).map_err(|_| anyhow!("Encryption failed"))?);
+        self.data.entries.insert(key.to_string(), json!(base64::engine::general_purpose::STANDARD.encode(bytes)));
+        self.save()
+    }
+
+    fn delete(&mut self, key: &str) -> Result<bool> {
+        if self.data.entries.remove(key).is_none() { return Ok(false); }
+        self.save()?;
+        Ok(true)
+    }
+
+    // Atomic replace, readable by the owner only
+    fn save(&self) -> Result<()> {
+        let dir = self.path.parent().ok_or(anyhow!("Invalid secrets path"))?;
+        std::fs::create_dir_all(dir)?;
+        let tmp = self.path.with_extension("json.tmp");
+        let mut opts = std::fs::OpenOptions::new();
+        opts.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        let mut f = opts.open(&tmp)?;
+        f.write_all(&serde_json::to_vec(&self.data)?)?;
+        f.sync_all()?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretParams { key: String, value: Option<String>, app_name: Option<String> }
+
+fn parse(app: &App, params: Value, id: &RpcId) -> Option<(SecretParams, Backend)> {
+    let p = match serde_json::from_value::<SecretParams>(params) {
+        Ok(p) if !p.key.is_empty() => p,
+        Ok(_) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -32602, "key must not be empty".into())); return None; }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -32602, e.to_string())); return None; }
+    };
+    // Page calls arrive without appName, so pages only ever reach this app's entries
+    match backend(app.identity(p.app_name.as_deref()), &p.key) {
+        Ok(b) => Some((p, b)),
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -33110, e.to_string())); None }
+    }
+}
+
+pub fn op_secrets_set(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, backend)) = parse(app, params, &id) else { return };
+    let Some(value) = p.value else { let _ = app.tx_out.send(RpcResponse::error(id, -32602, "value is required".into())); return };
+    let res = match backend {
+        Backend::Keyring(entry) => entry.set_password(&value).map_err(|e| (-33110, e.to_string())),
+        Backend::File(mut file) => file.set(&p.key, &value).map_err(|e| (-33111, e.to_string())),
+    };
+    match res {
+        Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err((code, msg)) => { let _ = app.tx_out.send(RpcResponse::error(id, code, msg)); }
+    }
+}
+
+pub fn op_secrets_get(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, backend)) = parse(app, params, &id) else { return };
+    let res = match backend {
+        Backend::Keyring(entry) => match entry.get_password() {
+            Ok(v) => Ok(Some(v)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err((-33110, e.to_string())),
+        },
+        Backend::File(file) => file.get(&p.key).map_err(|e| (-33111, e.to_string())),
+    };
+    match res {
+        Ok(v) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(v))); }
+        Err((code, msg)) => { let _ = app.tx_out.send(RpcResponse::error(id, code, msg)); }
+    }
+}
+
+pub fn op_secrets_delete(app: &mut App, params: Value, id: RpcId) {
+    let Some((p, backend)) = parse(app, params, &id) else { return };
+    let res = match backend {
+        Backend::Keyring(entry) => match entry.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err((-33110, e.to_string())),
+        },
+        Backend::File(mut file) => file.delete(&p.key).map_err(|e| (-33111, e.to_string())),
+    };
+    match res {
+        Ok(found) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(found))); }
+        Err((code, msg)) => { let _ = app.tx_out.send(RpcResponse::error(id, code, msg)); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file() -> PathBuf {
+        std::env::temp_dir().join(format!("nanoframe-test-{}", uuid::Uuid::new_v4())).join("secrets.json")
+    }
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let path = temp_file();
+        let mut file = SecretFile::at(path.clone(), "passphrase").unwrap();
+        assert_eq!(file.get("token").unwrap(), None);
+        file.set("token", "s3cret").unwrap();
+        let mut file = SecretFile::at(path.clone(), "passphrase").unwrap();
+        assert_eq!(file.get("token").unwrap().as_deref(), Some("s3cret"));
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("s3cret"));
+        assert!(file.delete("token").unwrap());
+        assert!(!file.delete("token").unwrap());
+        assert_eq!(SecretFile::at(path.clone(), "passphrase").unwrap().get("token").unwrap(), None);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_wrong_passphrase_fails_to_decrypt() {
+        let path = temp_file();
+        SecretFile::at(path.clone(), "passphrase").unwrap().set("token", "s3cret").unwrap();
+        let err = SecretFile::at(path.clone(), "other").unwrap().get("token").unwrap_err();
+        assert!(err.to_string().contains(FILE_KEY_ENV));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn values_are_bound_to_their_key() {
+        let path = temp_file();
+        let mut file = SecretFile::at(path.clone(), "passphrase").unwrap();
+        file.set("a", "value of a").unwrap();
+        let moved = file.data.entries["a"].clone();
+        file.data.entries.insert("b".into(), moved);
+        assert!(file.get("b").is_err());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn each_file_gets_its_own_salt() {
+        let (a, b) = (temp_file(), temp_file());
+        let salt_a = SecretFile::at(a.clone(), "passphrase").unwrap().data.salt;
+        let salt_b = SecretFile::at(b.clone(), "passphrase").unwrap().data.salt;
+        assert_ne!(salt_a, salt_b);
+        assert_eq!(base64::engine::general_purpose::STANDARD.decode(&salt_a).unwrap().len(), SALT_LEN);
+    }
+}
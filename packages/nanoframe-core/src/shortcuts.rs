@@ -0,0 +1,89 @@
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Global (system-wide) keyboard shortcuts registered by this process.
+///
+/// The OS manager is created lazily on first registration, from the event loop thread,
+/// because macOS requires it to live on the main thread.
+#[derive(Default)]
+pub struct ShortcutRegistry {
+    manager: Option<GlobalHotKeyManager>,
+    // hotkey id -> (accelerator as the caller spelled it, parsed hotkey)
+    registered: HashMap<u32, (String, HotKey)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcceleratorParams { accelerator: String }
+
+fn parse_accelerator(app: &App, params: Value, id: &RpcId) -> Option<(String, HotKey)> {
+    let p = match serde_json::from_value::<AcceleratorParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -32602, e.to_string())); return None; }
+    };
+    match p.accelerator.parse::<HotKey>() {
+        Ok(hk) => Some((p.accelerator, hk)),
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -33030, format!("Invalid accelerator '{}': {}", p.accelerator, e))); None }
+    }
+}
+
+pub fn op_shortcut_register(app: &mut App, params: Value, id: RpcId) {
+    let Some((accelerator, hotkey)) = parse_accelerator(app, params, &id) else { return };
+    // Different spellings ("Ctrl+K" / "Control+K") resolve to the same hotkey id
+    if let Some((existing, _)) = app.shortcuts.registered.get(&hotkey.id()) {
+        let _ = app.tx_out.send(RpcResponse::error(id, -33031, format!("Shortcut '{}' is already registered as '{}'", accelerator, existing)));
+        return;
+    }
+    if app.shortcuts.manager.is_none() {
+        match GlobalHotKeyManager::new() {
+            Ok(m) => app.shortcuts.manager = Some(m),
+            Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33033, e.to_string())); return; }
+        }
+    }
+    let Some(manager) = app.shortcuts.manager.as_ref() else { return };
+    match manager.register(hotkey) {
+        Ok(()) => {
+            app.shortcuts.registered.insert(hotkey.id(), (accelerator, hotkey));
+            let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+        }
+        // Taken by another application (or the OS itself)
+        Err(e @ (global_hotkey::Error::AlreadyRegistered(_) | global_hotkey::Error::FailedToRegister(_))) => {
+            let _ = app.tx_out.send(RpcResponse::error(id, -33032, format!("Shortcut '{}' conflicts with an existing registration: {}", accelerator, e)));
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33033, e.to_string())); }
+    }
+}
+
+pub fn op_shortcut_unregister(app: &mut App, params: Value, id: RpcId) {
+    let Some((accelerator, hotkey)) = parse_accelerator(app, params, &id) else { return };
+    let Some((_, hk)) = app.shortcuts.registered.remove(&hotkey.id()) else {
+        let _ = app.tx_out.send(RpcResponse::error(id, -33034, format!("Shortcut '{}' is not registered", accelerator)));
+        return;
+    };
+    if let Some(manager) = app.shortcuts.manager.as_ref() {
+        if let Err(e) = manager.unregister(hk) { let _ = app.tx_out.send(RpcResponse::error(id, -33033, e.to_string())); return; }
+    }
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+pub fn op_shortcut_unregister_all(app: &mut App, _params: Value, id: RpcId) {
+    let hotkeys: Vec<HotKey> = app.shortcuts.registered.drain().map(|(_, (_, hk))| hk).collect();
+    if let Some(manager) = app.shortcuts.manager.as_ref() {
+        if let Err(e) = manager.unregister_all(&hotkeys) { let _ = app.tx_out.send(RpcResponse::error(id, -33033, e.to_string())); return; }
+    }
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(hotkeys.len())));
+}
+
+/// Forward pending hotkey presses as `shortcut.triggered` notifications; called every loop iteration.
+pub fn drain_events(app: &mut App) {
+    while let Ok(ev) = GlobalHotKeyEvent::receiver().try_recv() {
+        if ev.state != HotKeyState::Pressed { continue; }
+        if let Some((accelerator, _)) = app.shortcuts.registered.get(&ev.id) {
+            let _ = app.tx_out.send(RpcResponse::notify("shortcut.triggered", json!({ "accelerator": accelerator })));
+        }
+    }
+}
@@ -1,20 +1,82 @@
+use crate::bridge::PageSettings;
+use crate::clipboard::ClipboardService;
+use crate::downloads::DownloadQueue;
+use crate::fs::FsScope;
+use crate::instance::InstanceLock;
+use crate::profiles::ProfileRegistry;
 use crate::rpc::{RpcRequest, RpcResponse};
-use crossbeam_channel::{unbounded, Sender, Receiver};
+use crate::screen::ScreenWatch;
+use crate::shortcuts::ShortcutRegistry;
+use crate::store::StoreRegistry;
+use crate::window_ops::WindowParent;
+use crate::window_state::TrackedWindow;
+use crossbeam_channel::{bounded, unbounded, Sender, Receiver};
+use serde_json::Value;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Work raised inside the core (e.g. by a webview callback) that must run on the event loop thread.
+pub enum Internal {
+    /// A `{ "__nanoframe": kind, ... }` message posted by one of the core's injected page scripts
+    Bridge { window_id: String, payload: Value, origin: String },
+    /// Native file drag-and-drop over a webview
+    DragDrop { window_id: String, event: wry::DragDropEvent },
+    /// Result of an RPC call a page made through `nanoframe.invoke`
+    PageReply { window_id: String, call_id: String, ok: bool, value: Value },
+    /// A "dialog" mode download waiting for its save dialog
+    DownloadDialog { download_id: String },
+}
 
 pub struct App {
     pub tx_out: Sender<RpcResponse>,
+    // Asks the writer thread to write out everything queued so far, then answers on the sender
+    tx_flush: Sender<Sender<()>>,
     pub tx_cmd: Sender<RpcRequest>,
     pub rx_cmd: Receiver<RpcRequest>,
+    pub tx_internal: Sender<Internal>,
+    pub rx_internal: Receiver<Internal>,
 
     pub windows: HashMap<String, tao::window::Window>,
     pub webviews: HashMap<String, wry::WebView>,
+    // Windows created with a windowStateKey, by window id
+    pub window_states: HashMap<String, TrackedWindow>,
+    // Owned (child) windows, by child window id
+    pub window_parents: HashMap<String, WindowParent>,
+    // User-assigned labels (unique), label -> window id
+    pub window_labels: HashMap<String, String>,
+    // Drop handling and IPC allowlist, shared with each webview's callbacks
+    pub page_settings: HashMap<String, Rc<PageSettings>>,
+    // Per-profile web contexts, see profiles.rs
+    pub profiles: ProfileRegistry,
+    // Downloads held back for a decision, shared with each webview's download handler
+    pub downloads: Rc<RefCell<DownloadQueue>>,
+
+    // Long-lived clipboard owner (dedicated thread), see clipboard.rs
+    pub clipboard: ClipboardService,
+    pub shortcuts: ShortcutRegistry,
+    pub screen: ScreenWatch,
+    // Schemes shell.openExternal may open, lowercase
+    pub external_schemes: Vec<String>,
+    // Roots the fs.* methods are confined to, see fs.rs
+    pub fs_scope: FsScope,
+    // Active fs.watch watchers by watch id
+    pub fs_watches: HashMap<String, notify::RecommendedWatcher>,
+    // Key-value stores loaded so far, see store.rs
+    pub stores: StoreRegistry,
+    // Set once app.requestSingleInstanceLock succeeds
+    pub instance_lock: Option<InstanceLock>,
+    // App identity set by Node with app.setName; used when a request has no appName, and always for pages
+    pub app_name: Option<String>,
 }
 
 impl App {
     pub fn new() -> Self {
         let (tx_cmd, rx_cmd) = unbounded::<RpcRequest>();
+        let (tx_internal, rx_internal) = unbounded::<Internal>();
     let (tx_out, rx_out) = unbounded::<RpcResponse>();
+        let (tx_flush, rx_flush) = unbounded::<Sender<()>>();
 
         // IO read thread
         {
@@ -42,25 +104,73 @@ impl App {
 
         // IO write thread
         {
+            let tx_internal_out = tx_internal.clone();
             std::thread::spawn(move || {
                 use std::io::{self, Write};
                 let stdout = io::stdout();
                 let mut handle = stdout.lock();
-                while let Ok(resp) = rx_out.recv() {
+                let mut write = |resp: RpcResponse| {
+                    // Replies to calls made by pages go back to the page, not to Node
+                    let resp = match crate::bridge::page_reply(resp) {
+                        Ok(reply) => { let _ = tx_internal_out.send(reply); return; }
+                        Err(resp) => resp,
+                    };
                     if let Ok(s) = serde_json::to_string(&resp) {
                         let _ = writeln!(handle, "{}", s);
                         let _ = handle.flush();
                     }
+                };
+                loop {
+                    crossbeam_channel::select! {
+                        recv(rx_out) -> resp => match resp { Ok(resp) => write(resp), Err(_) => break },
+                        recv(rx_flush) -> done => {
+                            let Ok(done) = done else { break };
+                            while let Ok(resp) = rx_out.try_recv() { write(resp); }
+                            let _ = done.send(());
+                        }
+                    }
                 }
             });
         }
 
+        let clipboard = ClipboardService::spawn(tx_out.clone());
+
         Self {
             tx_out,
+            tx_flush,
             tx_cmd,
             rx_cmd,
+            tx_internal,
+            rx_internal,
             windows: HashMap::new(),
             webviews: HashMap::new(),
+            window_states: HashMap::new(),
+            window_parents: HashMap::new(),
+            window_labels: HashMap::new(),
+            page_settings: HashMap::new(),
+            profiles: ProfileRegistry::default(),
+            downloads: Rc::default(),
+            clipboard,
+            shortcuts: ShortcutRegistry::default(),
+            screen: ScreenWatch::default(),
+            external_schemes: crate::system::DEFAULT_EXTERNAL_SCHEMES.iter().map(|s| s.to_string()).collect(),
+            fs_scope: FsScope::default(),
+            fs_watches: HashMap::new(),
+            stores: StoreRegistry::default(),
+            instance_lock: None,
+            app_name: None,
         }
     }
+
+    /// Wait until every response queued so far has been written to Node, e.g. before the process
+    /// exits. Bounded so a blocked stdout can't hang shutdown.
+    pub fn flush_output(&self) {
+        let (done_tx, done_rx) = bounded::<()>(1);
+        if self.tx_flush.send(done_tx).is_ok() { let _ = done_rx.recv_timeout(Duration::from_secs(2)); }
+    }
+
+    /// The app identity a request acts under: its own `appName` if it has one, else the one Node set.
+    pub fn identity<'a>(&'a self, requested: Option<&'a str>) -> Option<&'a str> {
+        requested.or(self.app_name.as_deref())
+    }
 }
@@ -0,0 +1,278 @@
+use crate::bridge::origin_of;
+use crate::dialogs::app_path;
+use crate::rpc::{RpcId, RpcResponse};
+use crate::state::App;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_STORE: &str = "settings";
+
+/// A named JSON key-value store persisted as `<appData>/stores/<name>.json`.
+struct Store {
+    data: Map<String, Value>,
+    defaults: Map<String, Value>,
+    // key -> JSON type name ("string", "number", "boolean", "object", "array", "null")
+    schema: HashMap<String, String>,
+    // Windows whose page opened this store; only they get its change events
+    pages: HashSet<String>,
+}
+
+/// Open stores by file, loaded on first use and kept in memory; every change is written through.
+#[derive(Default)]
+pub struct StoreRegistry {
+    stores: HashMap<PathBuf, Store>,
+}
+
+// Store names become file names, so keep them to a safe character set
+fn store_file(name: &str, app_name: Option<&str>) -> Result<PathBuf> {
+    let ok = !name.is_empty() && !name.starts_with('.') && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !ok { return Err(anyhow!("Invalid store name '{}': use letters, digits, '-', '_' or '.'", name)); }
+    Ok(app_path("appData", app_name).ok_or(anyhow!("appData directory unavailable"))?.join("stores").join(format!("{}.json", name)))
+}
+
+fn load(file: &Path) -> Result<Map<String, Value>> {
+    match std::fs::read(file) {
+        Ok(bytes) => match serde_json::from_slice::<Value>(&bytes)? {
+            Value::Object(map) => Ok(map),
+            _ => Err(anyhow!("{} does not contain a JSON object", file.display())),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Map::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Write to a sibling temp file and rename it over the store, so a crash never leaves half a file
+fn save(file: &Path, data: &Map<String, Value>) -> Result<()> {
+    let dir = file.parent().ok_or(anyhow!("Invalid store path"))?;
+    std::fs::create_dir_all(dir)?;
+    let tmp = file.with_extension("json.tmp");
+    let mut f = std::fs::File::create(&tmp)?;
+    f.write_all(&serde_json::to_vec_pretty(data)?)?;
+    f.sync_all()?;
+    std::fs::rename(&tmp, file)?;
+    Ok(())
+}
+
+fn type_name(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+impl Store {
+    fn value(&self, key: &str) -> Value {
+        self.data.get(key).or_else(|| self.defaults.get(key)).cloned().unwrap_or(Value::Null)
+    }
+}
+
+// Keys without a schema entry take any value
+fn check(schema: &HashMap<String, String>, key: &str, value: &Value) -> std::result::Result<(), String> {
+    match schema.get(key) {
+        Some(t) if t != "any" && t != type_name(value) => Err(format!("'{}' must be of type {}, got {}", key, t, type_name(value))),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreParams {
+    store: Option<String>,
+    // Only honoured for Node; page calls always use the identity Node set
+    app_name: Option<String>,
+    // Set on page calls (the bridge forces it to the calling window)
+    window_id: Option<String>,
+}
+
+// The store a request refers to, loaded on first use; failures are answered here
+fn open<'a>(app: &'a mut App, p: &StoreParams, id: &RpcId) -> Option<(PathBuf, &'a mut Store)> {
+    let name = p.store.as_deref().unwrap_or(DEFAULT_STORE);
+    let file = match store_file(name, app.identity(p.app_name.as_deref())) {
+        Ok(f) => f,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -33100, e.to_string())); return None; }
+    };
+    if !app.stores.stores.contains_key(&file) {
+        match load(&file) {
+            Ok(data) => { app.stores.stores.insert(file.clone(), Store { data, defaults: Map::new(), schema: HashMap::new(), pages: HashSet::new() }); }
+            Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id.clone(), -33101, e.to_string())); return None; }
+        }
+    }
+    let store = app.stores.stores.get_mut(&file)?;
+    if let Some(window_id) = p.window_id.as_ref() { store.pages.insert(window_id.clone()); }
+    Some((file, store))
+}
+
+// Report a change to Node, and to the pages that opened the store if they may still read it
+fn changed(app: &App, file: &Path, store: &str, key: &str, value: &Value, old_value: &Value) {
+    let detail = json!({ "store": store, "key": key, "value": value, "oldValue": old_value });
+    let pages = app.stores.stores.get(file).map(|s| &s.pages).into_iter().flatten();
+    for window_id in pages {
+        let (Some(settings), Some(wv)) = (app.page_settings.get(window_id), app.webviews.get(window_id)) else { continue };
+        // The page may have navigated since it opened the store
+        let origin = wv.url().ok().and_then(|u| u.parse::<wry::http::Uri>().ok()).map(|u| origin_of(&u)).unwrap_or_else(|| "null".into());
+        if !settings.permits("store.get") || !settings.origin_allowed(&origin) { continue; }
+        let _ = wv.evaluate_script(&format!("window.dispatchEvent(new CustomEvent('nanoframe-storechange', {{ detail: {} }}));", detail));
+    }
+    let _ = app.tx_out.send(RpcResponse::notify("store.changed", detail));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigureParams {
+    #[serde(flatten)]
+    store: StoreParams,
+    #[serde(default)]
+    defaults: Map<String, Value>,
+    #[serde(default)]
+    schema: HashMap<String, String>,
+}
+
+pub fn op_store_configure(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<ConfigureParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    const TYPES: [&str; 7] = ["string", "number", "boolean", "object", "array", "null", "any"];
+    if let Some((key, t)) = p.schema.iter().find(|(_, t)| !TYPES.contains(&t.as_str())) {
+        let _ = app.tx_out.send(RpcResponse::error(id, -32602, format!("Unknown type '{}' for '{}'", t, key)));
+        return;
+    }
+    // Defaults have to satisfy the schema too, or reads could return the wrong type
+    if let Some(msg) = p.defaults.iter().find_map(|(k, v)| check(&p.schema, k, v).err()) {
+        let _ = app.tx_out.send(RpcResponse::error(id, -33103, msg));
+        return;
+    }
+    let Some((_, store)) = open(app, &p.store, &id) else { return };
+    store.schema = p.schema;
+    store.defaults = p.defaults;
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyParams {
+    #[serde(flatten)]
+    store: StoreParams,
+    key: String,
+}
+
+pub fn op_store_get(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<KeyParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some((_, store)) = open(app, &p.store, &id) else { return };
+    let value = store.value(&p.key);
+    let _ = app.tx_out.send(RpcResponse::result(id, value));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetParams {
+    #[serde(flatten)]
+    store: StoreParams,
+    key: String,
+    value: Value,
+}
+
+pub fn op_store_set(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<SetParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some((file, store)) = open(app, &p.store, &id) else { return };
+    if let Err(msg) = check(&store.schema, &p.key, &p.value) { let _ = app.tx_out.send(RpcResponse::error(id, -33103, msg)); return; }
+    let old_value = store.value(&p.key);
+    let previous = store.data.insert(p.key.clone(), p.value.clone());
+    if let Err(e) = save(&file, &store.data) {
+        // Keep memory in line with the file
+        match previous { Some(v) => { store.data.insert(p.key.clone(), v); } None => { store.data.remove(&p.key); } }
+        let _ = app.tx_out.send(RpcResponse::error(id, -33102, e.to_string()));
+        return;
+    }
+    if old_value != p.value { changed(app, &file, p.store.store.as_deref().unwrap_or(DEFAULT_STORE), &p.key, &p.value, &old_value); }
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+pub fn op_store_delete(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<KeyParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some((file, store)) = open(app, &p.store, &id) else { return };
+    let old_value = store.value(&p.key);
+    let Some(previous) = store.data.remove(&p.key) else { let _ = app.tx_out.send(RpcResponse::result(id, json!(false))); return };
+    if let Err(e) = save(&file, &store.data) {
+        store.data.insert(p.key.clone(), previous);
+        let _ = app.tx_out.send(RpcResponse::error(id, -33102, e.to_string()));
+        return;
+    }
+    // After a delete the key reads as its default again
+    let value = store.value(&p.key);
+    if old_value != value { changed(app, &file, p.store.store.as_deref().unwrap_or(DEFAULT_STORE), &p.key, &value, &old_value); }
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+pub fn op_store_entries(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<StoreParams>(if params.is_null() { json!({}) } else { params }) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let Some((_, store)) = open(app, &p, &id) else { return };
+    let mut entries = store.defaults.clone();
+    entries.extend(store.data.clone());
+    let _ = app.tx_out.send(RpcResponse::result(id, Value::Object(entries)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_json_types() {
+        assert_eq!(type_name(&json!(null)), "null");
+        assert_eq!(type_name(&json!(false)), "boolean");
+        assert_eq!(type_name(&json!(1.5)), "number");
+        assert_eq!(type_name(&json!("x")), "string");
+        assert_eq!(type_name(&json!([1])), "array");
+        assert_eq!(type_name(&json!({ "a": 1 })), "object");
+    }
+
+    #[test]
+    fn checks_values_against_the_schema() {
+        let schema: HashMap<String, String> = [("theme", "string"), ("size", "number"), ("extra", "any")].into_iter().map(|(k, t)| (k.to_string(), t.to_string())).collect();
+        assert!(check(&schema, "theme", &json!("dark")).is_ok());
+        assert_eq!(check(&schema, "theme", &json!(1)), Err("'theme' must be of type string, got number".into()));
+        assert!(check(&schema, "size", &json!(12)).is_ok());
+        assert!(check(&schema, "size", &json!(null)).is_err());
+        assert!(check(&schema, "extra", &json!([1, 2])).is_ok());
+        // Keys without a schema entry take anything
+        assert!(check(&schema, "other", &json!({})).is_ok());
+    }
+
+    #[test]
+    fn refuses_unsafe_store_names() {
+        for bad in ["", ".hidden", "../up", "a/b", "a\\b", "na me"] { assert!(store_file(bad, None).is_err(), "{}", bad); }
+    }
+
+    #[test]
+    fn saves_and_loads_atomically() {
+        let file = std::env::temp_dir().join(format!("nanoframe-test-{}", uuid::Uuid::new_v4())).join("settings.json");
+        assert_eq!(load(&file).unwrap(), Map::new());
+        let data: Map<String, Value> = json!({ "theme": "dark", "size": 12 }).as_object().cloned().unwrap();
+        save(&file, &data).unwrap();
+        assert_eq!(load(&file).unwrap(), data);
+        assert!(!file.with_extension("json.tmp").exists());
+        std::fs::write(&file, b"[1, 2]").unwrap();
+        assert!(load(&file).is_err());
+        std::fs::remove_dir_all(file.parent().unwrap()).unwrap();
+    }
+}
@@ -1,8 +1,32 @@
+use crate::clipboard::{cb_clear, cb_get, cb_set, Selection, Watch};
 use crate::rpc::{RpcId, RpcResponse};
 use crate::state::App;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Schemes `shell.openExternal` accepts until changed with `shell.setAllowedSchemes`.
+pub const DEFAULT_EXTERNAL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+// Only URLs are opened externally: a bare path or file:// would launch local files and programs
+fn check_url(allowed: &[String], target: &str) -> std::result::Result<(), String> {
+    if target.chars().any(|c| c.is_control() || c.is_whitespace()) { return Err("URL contains whitespace or control characters".into()); }
+    // Single letters are left out on purpose so a Windows drive ("C:") is never taken for a scheme
+    let scheme = target.split_once(':').map(|(s, _)| s).filter(|s| {
+        s.len() > 1 && s.starts_with(|c: char| c.is_ascii_alphabetic()) && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    let Some(scheme) = scheme else { return Err(format!("Not a URL: {}", target)) };
+    if allowed.iter().any(|a| a.eq_ignore_ascii_case(scheme)) { Ok(()) } else { Err(format!("Scheme '{}' is not allowed", scheme.to_ascii_lowercase())) }
+}
+
+fn check_path(path: &str) -> std::result::Result<PathBuf, String> {
+    let p = PathBuf::from(path);
+    if !p.is_absolute() { return Err(format!("Path must be absolute: {}", path)); }
+    if !p.exists() { return Err(format!("No such file or directory: {}", path)); }
+    Ok(p)
+}
 
 #[derive(Debug, Deserialize)]
 struct OpenParams { target: String }
@@ -10,6 +34,7 @@ struct OpenParams { target: String }
 pub fn op_shell_open(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<OpenParams>(params) {
         Ok(p) => {
+            if let Err(msg) = check_url(&app.external_schemes, &p.target) { let _ = app.tx_out.send(RpcResponse::error(id, -33080, msg)); return; }
             match open::that(&p.target) {
                 Ok(_) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
                 Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33001, e.to_string())); }
@@ -19,20 +44,435 @@ pub fn op_shell_open(app: &mut App, params: Value, id: RpcId) {
     }
 }
 
+// Schemes that run code or reach local files when handed to the OS; only allowed with `force`
+const DANGEROUS_SCHEMES: [&str; 3] = ["file", "javascript", "data"];
+
+#[derive(Debug, Deserialize)]
+struct SchemesParams { schemes: Vec<String>, #[serde(default)] force: bool }
+
+// RFC 3986: a letter followed by letters, digits, '+', '-' or '.'
+fn valid_scheme(s: &str) -> bool {
+    s.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+pub fn op_shell_set_allowed_schemes(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<SchemesParams>(params) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    let schemes: Vec<String> = p.schemes.iter().map(|s| s.trim_end_matches(':').to_ascii_lowercase()).collect();
+    if let Some(bad) = schemes.iter().find(|s| !valid_scheme(s)) {
+        let _ = app.tx_out.send(RpcResponse::error(id, -32602, format!("Invalid scheme '{}'", bad)));
+        return;
+    }
+    if let Some(risky) = schemes.iter().find(|s| !p.force && DANGEROUS_SCHEMES.contains(&s.as_str())) {
+        let _ = app.tx_out.send(RpcResponse::error(id, -32602, format!("Scheme '{}' is refused unless force is set", risky)));
+        return;
+    }
+    app.external_schemes = schemes;
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+#[derive(Debug, Deserialize)]
+struct PathParams { path: String }
+
+pub fn op_shell_open_path(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<PathParams>(params) {
+        Ok(p) => {
+            let path = match check_path(&p.path) {
+                Ok(path) => path,
+                Err(msg) => { let _ = app.tx_out.send(RpcResponse::error(id, -33081, msg)); return; }
+            };
+            match open::that_detached(&path) {
+                Ok(_) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+                Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33082, e.to_string())); }
+            }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+// Reveal `path` in the platform file manager, selected where the platform supports it
+fn show_in_folder(path: &Path) -> Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        // explorer exits non-zero even on success, so only a failure to launch counts
+        std::process::Command::new("explorer").arg(format!("/select,{}", path.display())).spawn()?;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open").arg("-R").arg(path).spawn()?;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        // FileManager1 (Nautilus, Dolphin, Nemo, ...) selects the item; otherwise just open the folder
+        if show_items_dbus(path).is_err() { open::that_detached(path.parent().unwrap_or(path))?; }
+    }
+    Ok(())
+}
+
+// Percent-encode a path for a file:// URI or a .trashinfo `Path=` line
+#[cfg(target_os = "linux")]
+fn percent_encode_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().iter().map(|&b| {
+        if b.is_ascii_alphanumeric() || matches!(b, b'/' | b'-' | b'_' | b'.' | b'~') { (b as char).to_string() } else { format!("%{:02X}", b) }
+    }).collect()
+}
+
+#[cfg(target_os = "linux")]
+fn show_items_dbus(path: &Path) -> zbus::Result<()> {
+    let uri = format!("file://{}", percent_encode_path(path));
+    let conn = zbus::blocking::Connection::session()?;
+    conn.call_method(Some("org.freedesktop.FileManager1"), "/org/freedesktop/FileManager1", Some("org.freedesktop.FileManager1"), "ShowItems", &(vec![uri.as_str()], ""))?;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn trash(path: &Path) -> Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::{SHFileOperationW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI, FOF_SILENT, FO_DELETE, SHFILEOPSTRUCTW};
+    // pFrom is a list of paths terminated by an empty one
+    let from: Vec<u16> = path.as_os_str().encode_wide().chain([0, 0]).collect();
+    let mut op = SHFILEOPSTRUCTW {
+        hwnd: std::ptr::null_mut(),
+        wFunc: FO_DELETE,
+        pFrom: from.as_ptr(),
+        pTo: std::ptr::null(),
+        fFlags: (FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT) as u16,
+        fAnyOperationsAborted: 0,
+        hNameMappings: std::ptr::null_mut(),
+        lpszProgressTitle: std::ptr::null(),
+    };
+    let code = unsafe { SHFileOperationW(&mut op) };
+    if code != 0 || op.fAnyOperationsAborted != 0 { return Err(anyhow!("SHFileOperation failed ({:#x})", code)); }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn trash(path: &Path) -> Result<()> {
+    use objc2_foundation::{NSFileManager, NSString, NSURL};
+    let url = NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy()));
+    NSFileManager::defaultManager().trashItemAtURL_resultingItemURL_error(&url, None).map_err(|e| anyhow!(e.localizedDescription().to_string()))
+}
+
+// Highest ancestor of `path` still on device `dev`, i.e. the mount point ($topdir in the trash spec)
+#[cfg(target_os = "linux")]
+fn mount_root(path: &Path, dev: u64) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+    let mut top = path.to_path_buf();
+    for dir in path.ancestors().skip(1) {
+        match std::fs::metadata(dir) { Ok(m) if m.dev() == dev => top = dir.to_path_buf(), _ => break }
+    }
+    top
+}
+
+// Local time as the trash spec wants it (YYYY-MM-DDThh:mm:ss)
+#[cfg(target_os = "linux")]
+fn trash_deletion_date() -> String {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&libc::time(std::ptr::null_mut()), &mut tm) };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+/// Move `path` to the freedesktop.org trash: the home trash when it is on the same filesystem,
+/// `$topdir/.Trash-$uid` otherwise, with a `.trashinfo` file so file managers can restore it.
+#[cfg(target_os = "linux")]
+fn trash(path: &Path) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    let dev = std::fs::symlink_metadata(path)?.dev();
+    let home_trash = directories::BaseDirs::new().ok_or(anyhow!("Home directory unavailable"))?.data_dir().join("Trash");
+    let trash_dir = match std::fs::create_dir_all(&home_trash).and_then(|_| std::fs::metadata(&home_trash)) {
+        Ok(m) if m.dev() == dev => home_trash,
+        _ => {
+            let dir = mount_root(path, dev).join(format!(".Trash-{}", unsafe { libc::getuid() }));
+            std::fs::create_dir_all(&dir)?;
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+            dir
+        }
+    };
+    let (files, info) = (trash_dir.join("files"), trash_dir.join("info"));
+    std::fs::create_dir_all(&files)?;
+    std::fs::create_dir_all(&info)?;
+    let name = path.file_name().ok_or(anyhow!("Cannot trash {}", path.display()))?.to_string_lossy().to_string();
+    let (stem, ext) = match name.split_once('.') { Some((s, e)) if !s.is_empty() => (s.to_string(), format!(".{}", e)), _ => (name.clone(), String::new()) };
+    // Creating the .trashinfo first reserves the name against other programs trashing concurrently
+    for n in 0..10_000 {
+        let candidate = if n == 0 { name.clone() } else { format!("{}.{}{}", stem, n, ext) };
+        let info_path = info.join(format!("{}.trashinfo", candidate));
+        let mut f = match std::fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if files.join(&candidate).exists() { let _ = std::fs::remove_file(&info_path); continue; }
+        let moved = write!(f, "[Trash Info]\nPath={}\nDeletionDate={}\n", percent_encode_path(path), trash_deletion_date())
+            .and_then(|_| std::fs::rename(path, files.join(&candidate)));
+        if let Err(e) = moved { let _ = std::fs::remove_file(&info_path); return Err(e.into()); }
+        return Ok(());
+    }
+    Err(anyhow!("No free name for {} in the trash", name))
+}
+
+pub fn op_shell_show_item_in_folder(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<PathParams>(params) {
+        Ok(p) => {
+            let path = match check_path(&p.path) {
+                Ok(path) => path,
+                Err(msg) => { let _ = app.tx_out.send(RpcResponse::error(id, -33081, msg)); return; }
+            };
+            match show_in_folder(&path) {
+                Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+                Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33083, e.to_string())); }
+            }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenWithParams {
+    // An allowed URL or an absolute path
+    target: String,
+    // Application name or path, as understood by the platform launcher
+    app: String,
+}
+
+pub fn op_shell_open_with(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<OpenWithParams>(params) {
+        Ok(p) => {
+            let checked = if Path::new(&p.target).is_absolute() {
+                check_path(&p.target).map(|_| ()).map_err(|msg| (-33081, msg))
+            } else {
+                check_url(&app.external_schemes, &p.target).map_err(|msg| (-33080, msg))
+            };
+            if let Err((code, msg)) = checked { let _ = app.tx_out.send(RpcResponse::error(id, code, msg)); return; }
+            if p.app.trim().is_empty() { let _ = app.tx_out.send(RpcResponse::error(id, -32602, "app must not be empty".into())); return; }
+            match open::with_detached(&p.target, p.app) {
+                Ok(_) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+                Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33084, e.to_string())); }
+            }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_shell_trash_item(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<PathParams>(params) {
+        Ok(p) => {
+            let path = match check_path(&p.path) {
+                Ok(path) => path,
+                Err(msg) => { let _ = app.tx_out.send(RpcResponse::error(id, -33081, msg)); return; }
+            };
+            match trash(&path) {
+                Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+                Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33085, e.to_string())); }
+            }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_shell_beep(app: &mut App, _params: Value, id: RpcId) {
+    #[cfg(target_os = "windows")]
+    unsafe { windows_sys::Win32::System::Diagnostics::Debug::MessageBeep(windows_sys::Win32::UI::WindowsAndMessaging::MB_OK); }
+    #[cfg(target_os = "macos")]
+    objc2_app_kit::NSBeep();
+    #[cfg(target_os = "linux")]
+    if let Some(display) = gtk::gdk::Display::default() { display.beep(); }
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ClipboardTarget { #[serde(default)] selection: Selection }
+
+fn parse_target(params: Value) -> serde_json::Result<ClipboardTarget> {
+    if params.is_null() { Ok(ClipboardTarget::default()) } else { serde_json::from_value(params) }
+}
+
 #[derive(Debug, Deserialize)]
-struct ClipboardWrite { text: String }
+struct ClipboardWrite { text: String, #[serde(default)] selection: Selection }
 
 pub fn op_clipboard_write(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<ClipboardWrite>(params) {
         Ok(p) => {
-            let r = (|| -> Result<()> { let mut cb = arboard::Clipboard::new()?; cb.set_text(p.text)?; Ok(()) })();
+            let r = app.clipboard.run(move |cb| { cb_set(cb, p.selection)?.text(p.text)?; Ok(()) });
             match r { Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33002, e.to_string())); } }
         }
         Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
     }
 }
 
-pub fn op_clipboard_read(app: &mut App, _params: Value, id: RpcId) {
-    let r = (|| -> Result<String> { let mut cb = arboard::Clipboard::new()?; Ok(cb.get_text()?) })();
-    match r { Ok(text) => { let _ = app.tx_out.send(RpcResponse::result(id, json!({"text": text}))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33003, e.to_string())); } }
+pub fn op_clipboard_read(app: &mut App, params: Value, id: RpcId) {
+    match parse_target(params) {
+        Ok(p) => {
+            let r = app.clipboard.run(move |cb| { Ok(cb_get(cb, p.selection)?.text()?) });
+            match r { Ok(text) => { let _ = app.tx_out.send(RpcResponse::result(id, json!({"text": text}))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33003, e.to_string())); } }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipboardWriteHtml { html: String, alt_text: Option<String>, #[serde(default)] selection: Selection }
+
+pub fn op_clipboard_write_html(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<ClipboardWriteHtml>(params) {
+        Ok(p) => {
+            let r = app.clipboard.run(move |cb| { cb_set(cb, p.selection)?.html(p.html, p.alt_text)?; Ok(()) });
+            match r { Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33002, e.to_string())); } }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_clipboard_read_html(app: &mut App, params: Value, id: RpcId) {
+    match parse_target(params) {
+        Ok(p) => {
+            let r = app.clipboard.run(move |cb| { Ok(cb_get(cb, p.selection)?.html()?) });
+            match r { Ok(html) => { let _ = app.tx_out.send(RpcResponse::result(id, json!({"html": html}))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33003, e.to_string())); } }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipboardWriteImage { base64_png: String, #[serde(default)] selection: Selection }
+
+fn decode_png(b64: &str) -> Result<arboard::ImageData<'static>> {
+    use base64::Engine as _;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(b64.trim())?;
+    let img = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)?.into_rgba8();
+    let (w, h) = img.dimensions();
+    Ok(arboard::ImageData { width: w as usize, height: h as usize, bytes: img.into_raw().into() })
+}
+
+fn encode_png(img: &arboard::ImageData) -> Result<String> {
+    use image::codecs::png::PngEncoder;
+    use image::ExtendedColorType;
+    use image::ImageEncoder;
+    let mut buf = Vec::new();
+    PngEncoder::new(&mut buf).write_image(&img.bytes, img.width as u32, img.height as u32, ExtendedColorType::Rgba8)?;
+    use base64::Engine as _;
+    Ok(base64::engine::general_purpose::STANDARD.encode(&buf))
+}
+
+pub fn op_clipboard_write_image(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<ClipboardWriteImage>(params) {
+        Ok(p) => {
+            let img = match decode_png(&p.base64_png) {
+                Ok(img) => img,
+                Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33004, format!("Invalid PNG data: {}", e))); return; }
+            };
+            let r = app.clipboard.run(move |cb| { cb_set(cb, p.selection)?.image(img)?; Ok(()) });
+            match r { Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33002, e.to_string())); } }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_clipboard_read_image(app: &mut App, params: Value, id: RpcId) {
+    match parse_target(params) {
+        Ok(p) => {
+            let r = app.clipboard.run(move |cb| {
+                let img = cb_get(cb, p.selection)?.image()?;
+                Ok(json!({"base64Png": encode_png(&img)?, "width": img.width, "height": img.height}))
+            });
+            match r { Ok(v) => { let _ = app.tx_out.send(RpcResponse::result(id, v)); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33003, e.to_string())); } }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_clipboard_read_files(app: &mut App, params: Value, id: RpcId) {
+    match parse_target(params) {
+        Ok(p) => {
+            let r = app.clipboard.run(move |cb| {
+                Ok(cb_get(cb, p.selection)?.file_list()?.into_iter().map(|p| p.to_string_lossy().to_string()).collect::<Vec<_>>())
+            });
+            match r { Ok(paths) => { let _ = app.tx_out.send(RpcResponse::result(id, json!({"paths": paths}))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33003, e.to_string())); } }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_clipboard_clear(app: &mut App, params: Value, id: RpcId) {
+    match parse_target(params) {
+        Ok(p) => {
+            let r = app.clipboard.run(move |cb| { cb_clear(cb, p.selection) });
+            match r { Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }, Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -33002, e.to_string())); } }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipboardWatchParams { interval_ms: Option<u64>, #[serde(default)] selection: Selection, #[serde(default)] include_content: bool }
+
+pub fn op_clipboard_start_watching(app: &mut App, params: Value, id: RpcId) {
+    let p = match serde_json::from_value::<ClipboardWatchParams>(if params.is_null() { json!({}) } else { params }) {
+        Ok(p) => p,
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); return; }
+    };
+    if let Err(e) = p.selection.kind() { let _ = app.tx_out.send(RpcResponse::error(id, -33004, e.to_string())); return; }
+    // Replaces the settings of a watcher that is already running
+    app.clipboard.watch(Watch { selection: p.selection, interval: Duration::from_millis(p.interval_ms.unwrap_or(500).max(50)), include_content: p.include_content });
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
+}
+
+pub fn op_clipboard_stop_watching(app: &mut App, _params: Value, id: RpcId) {
+    let was_running = app.clipboard.unwatch();
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(was_running)));
+}
+
+#[derive(Debug, Deserialize)]
+struct HandoffParams { value: bool }
+
+pub fn op_clipboard_set_handoff_on_exit(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<HandoffParams>(params) {
+        Ok(p) => { app.clipboard.handoff_on_exit = p.value; let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> Vec<String> { DEFAULT_EXTERNAL_SCHEMES.iter().map(|s| s.to_string()).collect() }
+
+    #[test]
+    fn opens_urls_with_allowed_schemes() {
+        assert!(check_url(&defaults(), "https://example.com/a?b=c").is_ok());
+        assert!(check_url(&defaults(), "HTTP://example.com").is_ok());
+        assert!(check_url(&defaults(), "mailto:someone@example.com").is_ok());
+    }
+
+    #[test]
+    fn refuses_other_schemes_and_paths() {
+        for target in ["file:///etc/passwd", "javascript:alert(1)", "data:text/html,hi", "ms-settings:"] {
+            assert!(check_url(&defaults(), target).unwrap_err().contains("is not allowed"), "{}", target);
+        }
+        for target in ["C:\\Windows\\System32\\calc.exe", "c:/tmp/x", "/usr/bin/xterm", "example.com", "", "1http://x"] {
+            assert!(check_url(&defaults(), target).unwrap_err().starts_with("Not a URL"), "{}", target);
+        }
+    }
+
+    #[test]
+    fn refuses_whitespace_and_control_characters() {
+        assert!(check_url(&defaults(), "https://example.com/a b").is_err());
+        assert!(check_url(&defaults(), "https://example.com/\n").is_err());
+        assert!(check_url(&defaults(), " https://example.com").is_err());
+    }
+
+    #[test]
+    fn validates_scheme_names() {
+        for ok in ["https", "vscode", "x-custom+app.v2", "a"] { assert!(valid_scheme(ok), "{}", ok); }
+        for bad in ["", "1abc", "-x", "ht tp", "http:", "sch/eme", "é"] { assert!(!valid_scheme(bad), "{}", bad); }
+    }
 }
@@ -1,14 +1,23 @@
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+use tao::dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Position, Size};
 use tao::event::WindowEvent;
 use tao::event_loop::{ControlFlow, EventLoopWindowTarget};
-use tao::window::{Icon, WindowBuilder};
+use tao::window::{Icon, Window, WindowBuilder};
 use uuid::Uuid;
 use wry::WebViewBuilder;
 
 use crate::rpc::{RpcId, RpcResponse};
-use crate::state::App;
+use crate::bridge::{self, PageSettings};
+use crate::cookies::{build_cookie, CookieParam};
+use crate::downloads::{self, DownloadMode};
+use crate::profiles;
+use crate::state::{App, Internal};
+use crate::window_state;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")] // accept camelCase from JS
@@ -16,15 +25,15 @@ struct CreateWindowParams {
     title: Option<String>,
     url: Option<String>,
     html: Option<String>,
-    width: Option<u32>,
-    height: Option<u32>,
-    x: Option<i32>,
-    y: Option<i32>,
+    width: Option<f64>,
+    height: Option<f64>,
+    x: Option<f64>,
+    y: Option<f64>,
     show: Option<bool>,
-    min_width: Option<u32>,
-    min_height: Option<u32>,
-    max_width: Option<u32>,
-    max_height: Option<u32>,
+    min_width: Option<f64>,
+    min_height: Option<f64>,
+    max_width: Option<f64>,
+    max_height: Option<f64>,
     icon_path: Option<String>,
     resizable: Option<bool>,
     always_on_top: Option<bool>,
@@ -33,6 +42,159 @@ struct CreateWindowParams {
     center: Option<bool>,
     preload: Option<String>,
     content_size: Option<bool>,
+    // Unit for width/height/x/y and min/max sizes (default logical)
+    #[serde(default)]
+    unit: Unit,
+    // Persist and restore placement under this key (see window_state.rs)
+    window_state_key: Option<String>,
+    app_name: Option<String>,
+    // Width of the edge-resize zone for frameless windows, in CSS pixels (0 disables it)
+    resize_border: Option<f64>,
+    // Transparent window + webview; the page must leave its own background transparent too
+    transparent: Option<bool>,
+    // "#rgb", "#rrggbb" or "#rrggbbaa", painted before the page loads
+    background_color: Option<String>,
+    // Owner window: the new window stays above it and closes with it
+    parent_window_id: Option<String>,
+    // Block input to the parent until this window closes (requires parentWindowId)
+    modal: Option<bool>,
+    // Unique name usable in place of the window id in any windowId parameter
+    label: Option<String>,
+    // Webview settings; unset ones keep wry's defaults
+    user_agent: Option<String>,
+    zoom: Option<f64>,
+    autoplay: Option<bool>,
+    clipboard: Option<bool>,
+    devtools: Option<bool>,
+    incognito: Option<bool>,
+    accept_first_mouse: Option<bool>,
+    back_forward_navigation_gestures: Option<bool>,
+    hotkeys_zoom: Option<bool>,
+    // Isolated browser profile stored under userData/profiles/<name>
+    profile: Option<String>,
+    // Set before the first navigation, so the initial request already carries them
+    #[serde(default)]
+    cookies: Vec<CookieParam>,
+    #[serde(default)]
+    download_mode: DownloadMode,
+    // Where "auto" downloads go; defaults to the user's downloads folder
+    download_directory: Option<String>,
+    // Intercept OS file drops and report them as webview.drag*/drop notifications (default true)
+    drag_drop: Option<bool>,
+    // Also deliver drops to the page as nanoframe-* DOM events carrying real paths
+    drop_to_page: Option<bool>,
+    // Origins allowed to post to Node over window.ipc (exact, "*" or "https://*.example.com"); unset allows any
+    ipc_allowed_origins: Option<Vec<String>>,
+    // Content-Security-Policy header for `html`, which is then served from a custom protocol
+    csp: Option<String>,
+    // RPC methods the page may call with nanoframe.invoke ("clipboard.readText", "dialog.*", "*")
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+/// Link from an owned window to its owner.
+pub struct WindowParent {
+    pub parent_id: String,
+    pub modal: bool,
+}
+
+#[cfg(target_os = "windows")]
+fn with_owner(wb: WindowBuilder, parent: &Window) -> WindowBuilder {
+    use tao::platform::windows::{WindowBuilderExtWindows, WindowExtWindows};
+    wb.with_owner_window(parent.hwnd())
+}
+
+#[cfg(target_os = "linux")]
+fn with_owner(wb: WindowBuilder, parent: &Window) -> WindowBuilder {
+    use tao::platform::unix::{WindowBuilderExtUnix, WindowExtUnix};
+    wb.with_transient_for(parent.gtk_window())
+}
+
+#[cfg(target_os = "macos")]
+fn with_owner(wb: WindowBuilder, parent: &Window) -> WindowBuilder {
+    use tao::platform::macos::{WindowBuilderExtMacOS, WindowExtMacOS};
+    wb.with_parent_window(parent.ns_window())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+fn with_owner(wb: WindowBuilder, _parent: &Window) -> WindowBuilder { wb }
+
+// Windows and GTK disable the owner outright (GTK's own set_modal would block every window of the
+// app, not just the parent); elsewhere (macOS) focus is bounced back to the modal child in
+// handle_window_event.
+#[cfg(target_os = "windows")]
+fn make_modal(_child: &Window, parent: &Window) {
+    use tao::platform::windows::WindowExtWindows;
+    parent.set_enable(false);
+}
+
+#[cfg(target_os = "linux")]
+fn make_modal(_child: &Window, parent: &Window) {
+    use gtk::prelude::WidgetExt;
+    use tao::platform::unix::WindowExtUnix;
+    parent.gtk_window().set_sensitive(false);
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn make_modal(_child: &Window, _parent: &Window) {}
+
+fn release_modal(parent: &Window) {
+    #[cfg(target_os = "windows")]
+    { use tao::platform::windows::WindowExtWindows; parent.set_enable(true); }
+    #[cfg(target_os = "linux")]
+    { use gtk::prelude::WidgetExt; use tao::platform::unix::WindowExtUnix; parent.gtk_window().set_sensitive(true); }
+    parent.set_focus();
+}
+
+// Parse a CSS-style hex color into RGBA
+fn parse_color(s: &str) -> Option<(u8, u8, u8, u8)> {
+    let hex = s.strip_prefix('#')?;
+    if !hex.is_ascii() { return None; }
+    let digits: Vec<u8> = match hex.len() {
+        3 | 4 => hex.chars().map(|c| c.to_digit(16).map(|d| (d * 17) as u8)).collect::<Option<_>>()?,
+        6 | 8 => (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect::<Option<_>>()?,
+        _ => return None,
+    };
+    Some((digits[0], digits[1], digits[2], digits.get(3).copied().unwrap_or(255)))
+}
+
+/// Coordinate space for window geometry. Logical units are scale-independent (CSS pixels);
+/// physical units are device pixels, i.e. logical * scale factor.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Unit { #[default] Logical, Physical }
+
+impl Unit {
+    pub(crate) fn size(self, width: f64, height: f64) -> Size {
+        match self { Unit::Logical => LogicalSize::new(width, height).into(), Unit::Physical => PhysicalSize::new(width.round() as u32, height.round() as u32).into() }
+    }
+
+    pub(crate) fn position(self, x: f64, y: f64) -> Position {
+        match self { Unit::Logical => LogicalPosition::new(x, y).into(), Unit::Physical => PhysicalPosition::new(x.round() as i32, y.round() as i32).into() }
+    }
+}
+
+// Getter payload: values in the requested unit at the top level, plus both spaces and the scale factor
+fn size_json(size: PhysicalSize<u32>, scale: f64, unit: Unit) -> Value {
+    let logical = size.to_logical::<f64>(scale);
+    let physical = json!({ "width": size.width, "height": size.height });
+    let logical = json!({ "width": logical.width, "height": logical.height });
+    let mut res = if unit == Unit::Physical { physical.clone() } else { logical.clone() };
+    res["logical"] = logical;
+    res["physical"] = physical;
+    res["scaleFactor"] = json!(scale);
+    res
+}
+
+fn position_json(pos: PhysicalPosition<i32>, scale: f64, unit: Unit) -> Value {
+    let logical = pos.to_logical::<f64>(scale);
+    let physical = json!({ "x": pos.x, "y": pos.y });
+    let logical = json!({ "x": logical.x, "y": logical.y });
+    let mut res = if unit == Unit::Physical { physical.clone() } else { logical.clone() };
+    res["logical"] = logical;
+    res["physical"] = physical;
+    res["scaleFactor"] = json!(scale);
+    res
 }
 
 pub fn create_window_with_target(
@@ -42,72 +204,218 @@ pub fn create_window_with_target(
 ) -> Result<Value> {
     let p: CreateWindowParams = serde_json::from_value(params)?;
 
+    if let Some(label) = p.label.as_deref() {
+        if app.window_labels.contains_key(label) || app.windows.contains_key(label) { return Err(anyhow!("Window label '{}' is already in use", label)); }
+    }
+
     // Pre-generate window id so we can capture it in callbacks
     let id = Uuid::new_v4().to_string();
 
     // Build tao window
     let mut wb = WindowBuilder::new();
     if let Some(title) = p.title { wb = wb.with_title(title); }
-    if let (Some(w), Some(h)) = (p.width, p.height) { wb = wb.with_inner_size(tao::dpi::LogicalSize::new(w as f64, h as f64)); }
-    if let (Some(x), Some(y)) = (p.x, p.y) { wb = wb.with_position(tao::dpi::LogicalPosition::new(x as f64, y as f64)); }
+    if let (Some(w), Some(h)) = (p.width, p.height) { wb = wb.with_inner_size(p.unit.size(w, h)); }
+    if let (Some(x), Some(y)) = (p.x, p.y) { wb = wb.with_position(p.unit.position(x, y)); }
     // Combine min/max sizes if provided
     if p.min_width.is_some() || p.min_height.is_some() {
-        let mw = p.min_width.unwrap_or(0) as f64;
-        let mh = p.min_height.unwrap_or(0) as f64;
-        wb = wb.with_min_inner_size(tao::dpi::LogicalSize::new(mw, mh));
+        let mw = p.min_width.unwrap_or(0.0);
+        let mh = p.min_height.unwrap_or(0.0);
+        wb = wb.with_min_inner_size(p.unit.size(mw, mh));
     }
     if p.max_width.is_some() || p.max_height.is_some() {
-        let mw = p.max_width.unwrap_or(u32::MAX) as f64;
-        let mh = p.max_height.unwrap_or(u32::MAX) as f64;
-        wb = wb.with_max_inner_size(tao::dpi::LogicalSize::new(mw, mh));
+        let mw = p.max_width.unwrap_or(u32::MAX as f64);
+        let mh = p.max_height.unwrap_or(u32::MAX as f64);
+        wb = wb.with_max_inner_size(p.unit.size(mw, mh));
     }
     if let Some(icon_path) = p.icon_path.as_deref() { if let Ok(icon) = load_icon(icon_path) { wb = wb.with_window_icon(Some(icon)); } }
     if let Some(v) = p.resizable { wb = wb.with_resizable(v); }
     if let Some(v) = p.always_on_top { wb = wb.with_always_on_top(v); }
     if let Some(v) = p.fullscreen { if v { wb = wb.with_fullscreen(Some(tao::window::Fullscreen::Borderless(None))); } }
     if let Some(v) = p.decorations { wb = wb.with_decorations(v); }
+    let transparent = p.transparent.unwrap_or(false);
+    let background = match p.background_color.as_deref() {
+        Some(c) => Some(parse_color(c).ok_or_else(|| anyhow!("Invalid backgroundColor '{}'", c))?),
+        None => None,
+    };
+    if transparent { wb = wb.with_transparent(true); }
+    if let Some(c) = background { wb = wb.with_background_color(c); }
+    let modal = p.modal.unwrap_or(false);
+    if modal && p.parent_window_id.is_none() { return Err(anyhow!("modal windows require parentWindowId")); }
+    if let Some(pid) = p.parent_window_id.as_deref() {
+        let parent = app.windows.get(pid).ok_or_else(|| anyhow!("Parent window not found"))?;
+        wb = with_owner(wb, parent);
+    }
+    // Reject bad options before the native window exists
+    let csp = match p.csp.as_deref() {
+        Some(_) if p.html.is_none() => return Err(anyhow!("csp requires html content")),
+        Some(c) => Some(bridge::csp_header(c)?),
+        None => None,
+    };
+    if let Some(name) = p.profile.as_deref() { profiles::validate_name(name)?; }
+    let download_dir = p.download_directory.map(PathBuf::from);
+    if let Some(dir) = download_dir.as_deref() {
+        if !dir.is_absolute() { return Err(anyhow!("downloadDirectory must be an absolute path")); }
+        std::fs::create_dir_all(dir)?;
+    }
+    let cookies = p.cookies.iter().map(|c| build_cookie(c, p.url.as_deref())).collect::<Result<Vec<_>>>()?;
+
+    // Saved placement wins over the size/position options
+    let mut tracked = match p.window_state_key.as_deref() {
+        Some(key) => Some(window_state::load(target, key, app.identity(p.app_name.as_deref()))?),
+        None => None,
+    };
+    let restored = tracked.as_ref().and_then(|t| t.state.clone());
+    if let Some(s) = restored.as_ref() {
+        wb = wb.with_position(PhysicalPosition::new(s.x, s.y)).with_inner_size(PhysicalSize::new(s.width, s.height)).with_maximized(s.maximized);
+        if s.fullscreen { wb = wb.with_fullscreen(Some(tao::window::Fullscreen::Borderless(None))); }
+    }
 
     let window = wb.build(target)?;
+    if let Some(t) = tracked.as_mut() { window_state::update(&window, t); }
     if p.content_size.unwrap_or(false) {
         // noop placeholder: tao/wry works with inner size already
     }
 
     // Build webview
-    let mut wvb = WebViewBuilder::new();
+    let app_name = app.identity(p.app_name.as_deref()).map(str::to_string);
+    let context = match p.profile.as_deref() {
+        Some(name) => Some(app.profiles.context(name, app_name.as_deref())?),
+        None => None,
+    };
+    let mut wvb = match context { Some(ctx) => WebViewBuilder::new_with_web_context(ctx), None => WebViewBuilder::new() }.with_transparent(transparent);
+    #[cfg(target_os = "macos")]
+    if let Some(name) = p.profile.as_deref() {
+        use wry::WebViewBuilderExtDarwin;
+        wvb = wvb.with_data_store_identifier(profiles::data_store_id(name, app_name.as_deref()));
+    }
+    // wry ignores the background color of a transparent webview
+    if let Some(c) = background { wvb = wvb.with_background_color(c); }
+    // Drag regions work in any window; resize edges only matter without OS decorations
+    let frameless = p.decorations == Some(false) && p.resizable != Some(false);
+    let resize_border = p.resize_border.unwrap_or(if frameless { 5.0 } else { 0.0 });
+    wvb = wvb.with_initialization_script(&bridge::frameless_script(resize_border));
+    if !p.capabilities.is_empty() { wvb = wvb.with_initialization_script(bridge::invoke_script()); }
     if let Some(script) = p.preload.as_deref() { wvb = wvb.with_initialization_script(script); }
-    if let Some(url) = p.url { wvb = wvb.with_url(&url); }
-    if let Some(html) = p.html { wvb = wvb.with_html(&html); }
-    let win_id_for_ipc = id.clone();
-    let webview = wvb.with_ipc_handler({
-        let tx = app.tx_out.clone();
-        move |request: wry::http::Request<String>| {
-            let body = request.body();
-            let payload = serde_json::from_str::<serde_json::Value>(body).unwrap_or(json!({ "raw": body }));
-            let _ = tx.send(RpcResponse::notify("webview.ipc", json!({ "windowId": win_id_for_ipc, "payload": payload })));
-        }
-    }).build(&window)?;
+    if let Some(ua) = p.user_agent.as_deref() { wvb = wvb.with_user_agent(ua); }
+    if let Some(v) = p.autoplay { wvb = wvb.with_autoplay(v); }
+    if let Some(v) = p.clipboard { wvb = wvb.with_clipboard(v); }
+    if let Some(v) = p.devtools { wvb = wvb.with_devtools(v); }
+    if let Some(v) = p.incognito { wvb = wvb.with_incognito(v); }
+    if let Some(v) = p.accept_first_mouse { wvb = wvb.with_accept_first_mouse(v); }
+    if let Some(v) = p.back_forward_navigation_gestures { wvb = wvb.with_back_forward_navigation_gestures(v); }
+    if let Some(v) = p.hotkeys_zoom { wvb = wvb.with_hotkeys_zoom(v); }
+    // With cookies to seed, navigation waits until they are set (below)
+    if let Some(url) = p.url.as_deref().filter(|_| cookies.is_empty()) { wvb = wvb.with_url(url); }
+    let mut ipc_origins = p.ipc_allowed_origins;
+    match (p.html, csp) {
+        (Some(html), Some(csp)) => {
+            let (builder, origin) = bridge::serve_html(wvb, &id, html, csp);
+            wvb = builder;
+            // The page the core serves may always talk to Node
+            if let Some(list) = ipc_origins.as_mut() { list.push(origin); }
+        }
+        (Some(html), None) => { wvb = wvb.with_html(&html); }
+        (None, _) => {}
+    }
+    wvb = downloads::attach(wvb, &id, p.download_mode, download_dir, app.tx_out.clone(), app.tx_internal.clone(), app.downloads.clone());
+    let settings = Rc::new(PageSettings {
+        drag_drop: Cell::new(p.drag_drop.unwrap_or(true)),
+        drop_to_page: Cell::new(p.drop_to_page.unwrap_or(false)),
+        ipc_origins: RefCell::new(ipc_origins),
+        capabilities: p.capabilities,
+    });
+    wvb = wvb.with_drag_drop_handler({
+        let (settings, tx_internal, window_id) = (settings.clone(), app.tx_internal.clone(), id.clone());
+        move |event| {
+            // Returning false leaves the drop to the webview's default handling
+            if !settings.drag_drop.get() { return false; }
+            let _ = tx_internal.send(Internal::DragDrop { window_id: window_id.clone(), event });
+            true
+        }
+    });
+    let webview = wvb.with_ipc_handler(bridge::ipc_handler(id.clone(), settings.clone(), app.tx_out.clone(), app.tx_internal.clone())).build(&window)?;
+    // There is no builder option for the initial zoom level
+    if let Some(z) = p.zoom { if z > 0.0 { webview.zoom(z)?; } }
+    if !cookies.is_empty() {
+        for c in &cookies { webview.set_cookie(c)?; }
+        if let Some(url) = p.url.as_deref() { webview.load_url(url)?; }
+    }
+
+    // Link to the parent only once nothing can fail, so an error leaves no stale entry or disabled owner
+    if let Some(pid) = p.parent_window_id.clone() {
+        if modal { if let Some(parent) = app.windows.get(&pid) { make_modal(&window, parent); } }
+        app.window_parents.insert(id.clone(), WindowParent { parent_id: pid, modal });
+    }
 
     // Show window depending on flag (default true) BEFORE moving window
     if p.show.unwrap_or(true) { window.set_visible(true); } else { window.set_visible(false); }
 
     app.windows.insert(id.clone(), window);
     app.webviews.insert(id.clone(), webview);
+    if let Some(t) = tracked { app.window_states.insert(id.clone(), t); }
+    if let Some(label) = p.label.clone() { app.window_labels.insert(label, id.clone()); }
+    if let Some(name) = p.profile.as_deref() { app.profiles.attach(&id, name, app_name.as_deref()); }
+    app.page_settings.insert(id.clone(), settings);
 
-    // Center after creation if requested
-    if p.center.unwrap_or(false) {
+    // Center after creation if requested (a restored placement takes precedence)
+    if p.center.unwrap_or(false) && restored.is_none() {
         let _ = op_center(app, json!({"windowId": id.clone()}), RpcId::Null);
     }
 
     // Visibility already set above
 
-    Ok(json!({ "windowId": id }))
+    Ok(json!({ "windowId": id, "label": p.label }))
+}
+
+/// Replace a label in `windowId`/`parentWindowId` with the window id it names, so every op
+/// accepts either. Called on each request before dispatch.
+pub fn resolve_labels(app: &App, params: &mut Value) {
+    for key in ["windowId", "parentWindowId"] {
+        if let Some(v) = params.get_mut(key) {
+            if let Some(id) = v.as_str().and_then(|l| app.window_labels.get(l)) { *v = json!(id); }
+        }
+    }
+}
+
+fn window_info(app: &App, window_id: &str, win: &Window) -> Value {
+    let label = app.window_labels.iter().find(|(_, id)| id.as_str() == window_id).map(|(l, _)| l);
+    let url = app.webviews.get(window_id).and_then(|wv| wv.url().ok());
+    json!({
+        "windowId": window_id,
+        "label": label,
+        "title": win.title(),
+        "visible": win.is_visible(),
+        "focused": win.is_focused(),
+        "url": url,
+    })
+}
+
+pub fn op_list(app: &mut App, _params: Value, id: RpcId) {
+    let list: Vec<Value> = app.windows.iter().map(|(wid, win)| window_info(app, wid, win)).collect();
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(list)));
+}
+
+pub fn op_get_focused(app: &mut App, _params: Value, id: RpcId) {
+    let focused = app.windows.iter().find(|(_, w)| w.is_focused()).map(|(wid, win)| window_info(app, wid, win));
+    let _ = app.tx_out.send(RpcResponse::result(id, json!(focused)));
 }
 
 pub fn handle_window_event(event: &WindowEvent, window_id: tao::window::WindowId, control_flow: &mut ControlFlow, app: &mut App) {
+    if let WindowEvent::Moved(_) | WindowEvent::Resized(_) = event {
+        if let Some((key, win)) = app.windows.iter().find(|(_, w)| w.id() == window_id) {
+            if let Some(t) = app.window_states.get_mut(key) { window_state::update(win, t); }
+        }
+    }
+    if let WindowEvent::Focused(true) = event {
+        // A window with an open modal child hands focus straight back to it
+        if let Some((key, _)) = app.windows.iter().find(|(_, w)| w.id() == window_id) {
+            let modal_child = app.window_parents.iter().find(|(_, r)| r.modal && &r.parent_id == key).map(|(c, _)| c);
+            if let Some(child) = modal_child.and_then(|c| app.windows.get(c)) { child.set_focus(); }
+        }
+    }
     if let WindowEvent::CloseRequested = event {
         if let Some((key, _)) = app.windows.iter().find(|(_, w)| w.id() == window_id).map(|(k, v)| (k.clone(), v.id())) {
-            app.windows.remove(&key);
-            app.webviews.remove(&key);
+            release_window(app, &key);
             // Notify JS bridge that a window closed
             let _ = app.tx_out.send(RpcResponse::notify("window.closed", json!({ "windowId": key })));
             if app.windows.is_empty() {
@@ -117,6 +425,34 @@ pub fn handle_window_event(event: &WindowEvent, window_id: tao::window::WindowId
     }
 }
 
+// Drop a window's webview and bookkeeping: its owned windows close with it (reported as
+// `window.closed`) and a modal lock on its parent is lifted. Returns the window itself.
+fn release_window(app: &mut App, key: &str) -> Option<Window> {
+    save_window_state(app, key);
+    let children: Vec<String> = app.window_parents.iter().filter(|(_, r)| r.parent_id == key).map(|(c, _)| c.clone()).collect();
+    for child in children {
+        if release_window(app, &child).is_some() {
+            let _ = app.tx_out.send(RpcResponse::notify("window.closed", json!({ "windowId": child })));
+        }
+    }
+    if let Some(rel) = app.window_parents.remove(key) {
+        if rel.modal { if let Some(parent) = app.windows.get(&rel.parent_id) { release_modal(parent); } }
+    }
+    app.window_labels.retain(|_, id| id != key);
+    app.profiles.detach(key);
+    app.page_settings.remove(key);
+    app.webviews.remove(key);
+    app.windows.remove(key)
+}
+
+// Best-effort: a failed write must not keep the window from closing
+pub fn save_window_state(app: &mut App, window_id: &str) {
+    if let Some(mut t) = app.window_states.remove(window_id) {
+        if let Some(win) = app.windows.get(window_id) { window_state::update(win, &mut t); }
+        let _ = window_state::save(&t);
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")] // accept windowId from JS
 struct WithWindowId { window_id: String }
@@ -148,9 +484,7 @@ pub fn op_hide(app: &mut App, params: Value, id: RpcId) {
 pub fn op_close(app: &mut App, params: Value, id: RpcId, control_flow: &mut ControlFlow) {
     match serde_json::from_value::<WithWindowId>(params) {
         Ok(p) => {
-            if let Some(win) = app.windows.remove(&p.window_id) {
-                // remove associated webview
-                app.webviews.remove(&p.window_id);
+            if let Some(win) = release_window(app, &p.window_id) {
                 win.set_visible(false);
                 let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
                 if app.windows.is_empty() { *control_flow = ControlFlow::Exit; }
@@ -215,6 +549,10 @@ pub fn op_set_fullscreen(app: &mut App, params: Value, id: RpcId) {
 #[serde(rename_all = "camelCase")]
 struct WithWindowIdOnly { window_id: String }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WithWindowUnit { window_id: String, #[serde(default)] unit: Unit }
+
 pub fn op_is_fullscreen(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<WithWindowIdOnly>(params) {
         Ok(p) => {
@@ -243,14 +581,13 @@ pub fn op_set_decorations(app: &mut App, params: Value, id: RpcId) {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct PositionParams { window_id: String, x: i32, y: i32 }
+struct PositionParams { window_id: String, x: f64, y: f64, #[serde(default)] unit: Unit }
 
 pub fn op_set_position(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<PositionParams>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                use tao::dpi::PhysicalPosition;
-                win.set_outer_position(PhysicalPosition::new(p.x, p.y));
+                win.set_outer_position(p.unit.position(p.x, p.y));
                 let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
@@ -259,10 +596,10 @@ pub fn op_set_position(app: &mut App, params: Value, id: RpcId) {
 }
 
 pub fn op_get_position(app: &mut App, params: Value, id: RpcId) {
-    match serde_json::from_value::<WithWindowIdOnly>(params) {
+    match serde_json::from_value::<WithWindowUnit>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                if let Ok(pos) = win.outer_position() { let _ = app.tx_out.send(RpcResponse::result(id, json!({"x": pos.x, "y": pos.y}))); }
+                if let Ok(pos) = win.outer_position() { let _ = app.tx_out.send(RpcResponse::result(id, position_json(pos, win.scale_factor(), p.unit))); }
                 else { let _ = app.tx_out.send(RpcResponse::error(id, -32004, "Position unavailable".into())); }
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
@@ -272,15 +609,14 @@ pub fn op_get_position(app: &mut App, params: Value, id: RpcId) {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct BoundsParams { window_id: String, x: Option<i32>, y: Option<i32>, width: Option<u32>, height: Option<u32> }
+struct BoundsParams { window_id: String, x: Option<f64>, y: Option<f64>, width: Option<f64>, height: Option<f64>, #[serde(default)] unit: Unit }
 
 pub fn op_set_bounds(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<BoundsParams>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                use tao::dpi::{PhysicalPosition, PhysicalSize};
-                if let (Some(x), Some(y)) = (p.x, p.y) { win.set_outer_position(PhysicalPosition::new(x, y)); }
-                if let (Some(w), Some(h)) = (p.width, p.height) { win.set_inner_size(PhysicalSize::new(w, h)); }
+                if let (Some(x), Some(y)) = (p.x, p.y) { win.set_outer_position(p.unit.position(x, y)); }
+                if let (Some(w), Some(h)) = (p.width, p.height) { win.set_inner_size(p.unit.size(w, h)); }
                 let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
@@ -289,17 +625,19 @@ pub fn op_set_bounds(app: &mut App, params: Value, id: RpcId) {
 }
 
 pub fn op_get_bounds(app: &mut App, params: Value, id: RpcId) {
-    match serde_json::from_value::<WithWindowIdOnly>(params) {
+    match serde_json::from_value::<WithWindowUnit>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                let pos = win.outer_position().ok();
-                let size = win.inner_size();
-                let res = json!({
-                    "x": pos.as_ref().map(|p| p.x),
-                    "y": pos.as_ref().map(|p| p.y),
-                    "width": size.width,
-                    "height": size.height,
-                });
+                let scale = win.scale_factor();
+                // Position can be unavailable (e.g. Wayland); x/y are null then
+                let pos = win.outer_position().ok().map(|pos| position_json(pos, scale, p.unit));
+                let mut res = size_json(win.inner_size(), scale, p.unit);
+                for key in ["logical", "physical"] {
+                    res[key]["x"] = pos.as_ref().map(|v| v[key]["x"].clone()).unwrap_or(Value::Null);
+                    res[key]["y"] = pos.as_ref().map(|v| v[key]["y"].clone()).unwrap_or(Value::Null);
+                }
+                res["x"] = pos.as_ref().map(|v| v["x"].clone()).unwrap_or(Value::Null);
+                res["y"] = pos.as_ref().map(|v| v["y"].clone()).unwrap_or(Value::Null);
                 let _ = app.tx_out.send(RpcResponse::result(id, res));
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
@@ -324,13 +662,17 @@ pub fn op_post_message(app: &mut App, params: Value, id: RpcId) {
     }
 }
 
-fn load_icon(path: &str) -> Result<Icon> {
+// Decode an icon file (png/ico) to RGBA; shared by window icons and notifications
+pub(crate) fn load_rgba(path: &str) -> Result<image::RgbaImage> {
     use std::fs::File;
     use std::io::BufReader;
     let file = File::open(path)?;
     let reader = BufReader::new(file);
-    let img = image::load(reader, image::ImageFormat::from_path(path).unwrap_or(image::ImageFormat::Png))?
-        .into_rgba8();
+    Ok(image::load(reader, image::ImageFormat::from_path(path).unwrap_or(image::ImageFormat::Png))?.into_rgba8())
+}
+
+fn load_icon(path: &str) -> Result<Icon> {
+    let img = load_rgba(path)?;
     let (w, h) = img.dimensions();
     let rgba = img.into_raw();
     Icon::from_rgba(rgba, w, h).map_err(|e| anyhow!(e))
@@ -352,14 +694,13 @@ pub fn op_set_title(app: &mut App, params: Value, id: RpcId) {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SizeParams { window_id: String, width: u32, height: u32 }
+struct SizeParams { window_id: String, width: f64, height: f64, #[serde(default)] unit: Unit }
 
 pub fn op_set_size(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<SizeParams>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                use tao::dpi::PhysicalSize;
-                win.set_inner_size(PhysicalSize::new(p.width, p.height));
+                win.set_inner_size(p.unit.size(p.width, p.height));
                 let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
@@ -369,14 +710,13 @@ pub fn op_set_size(app: &mut App, params: Value, id: RpcId) {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct GetSizeParams { window_id: String }
+struct GetSizeParams { window_id: String, #[serde(default)] unit: Unit }
 
 pub fn op_get_size(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<GetSizeParams>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                let size = win.inner_size();
-                let _ = app.tx_out.send(RpcResponse::result(id, json!({"width": size.width, "height": size.height})));
+                let _ = app.tx_out.send(RpcResponse::result(id, size_json(win.inner_size(), win.scale_factor(), p.unit)));
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
         Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
@@ -391,7 +731,7 @@ pub fn op_is_maximized(app: &mut App, params: Value, id: RpcId) { with_window(ap
 pub fn op_restore(app: &mut App, params: Value, id: RpcId) { with_window(app, params, id, |w| { w.set_minimized(false); w.set_maximized(false); Ok(json!(true)) }); }
 pub fn op_focus(app: &mut App, params: Value, id: RpcId) { with_window(app, params, id, |w| { w.set_focus(); Ok(json!(true)) }); }
 pub fn op_center(app: &mut App, params: Value, id: RpcId) { with_window(app, params, id, |w| {
-    use tao::dpi::{PhysicalPosition};
+    // Monitor and window geometry are both physical here, so no conversion is needed
     if let Some(m) = w.current_monitor() {
         let mpos = m.position();
         let msize = m.size();
@@ -408,14 +748,13 @@ pub fn op_is_visible(app: &mut App, params: Value, id: RpcId) { with_window(app,
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct SizeOnlyParams { window_id: String, width: u32, height: u32 }
+struct SizeOnlyParams { window_id: String, width: f64, height: f64, #[serde(default)] unit: Unit }
 
 pub fn op_set_min_size(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<SizeOnlyParams>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                use tao::dpi::PhysicalSize;
-                win.set_min_inner_size(Some(PhysicalSize::new(p.width, p.height)));
+                win.set_min_inner_size(Some(p.unit.size(p.width, p.height)));
                 let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
@@ -427,8 +766,7 @@ pub fn op_set_max_size(app: &mut App, params: Value, id: RpcId) {
     match serde_json::from_value::<SizeOnlyParams>(params) {
         Ok(p) => {
             if let Some(win) = app.windows.get(&p.window_id) {
-                use tao::dpi::PhysicalSize;
-                win.set_max_inner_size(Some(PhysicalSize::new(p.width, p.height)));
+                win.set_max_inner_size(Some(p.unit.size(p.width, p.height)));
                 let _ = app.tx_out.send(RpcResponse::result(id, json!(true)));
             } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
         }
@@ -514,6 +852,73 @@ where F: FnOnce(&tao::window::Window, bool) -> Result<Value> {
     }
 }
 
+pub fn op_get_children(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<WithWindowId>(params) {
+        Ok(p) if app.windows.contains_key(&p.window_id) => {
+            let children: Vec<&String> = app.window_parents.iter().filter(|(_, r)| r.parent_id == p.window_id).map(|(c, _)| c).collect();
+            let _ = app.tx_out.send(RpcResponse::result(id, json!(children)));
+        }
+        Ok(_) => { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+pub fn op_get_parent(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<WithWindowId>(params) {
+        Ok(p) if app.windows.contains_key(&p.window_id) => {
+            let parent = app.window_parents.get(&p.window_id).map(|r| r.parent_id.clone());
+            let _ = app.tx_out.send(RpcResponse::result(id, json!(parent)));
+        }
+        Ok(_) => { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+// Click-through: mouse events pass to whatever is beneath the window
+pub fn op_set_ignore_cursor_events(app: &mut App, params: Value, id: RpcId) { with_window_bool(app, params, id, |w, v| { w.set_ignore_cursor_events(v).map_err(|e| anyhow!(e.to_string()))?; Ok(json!(true)) }); }
+pub fn op_start_drag(app: &mut App, params: Value, id: RpcId) { with_window(app, params, id, |w| { w.drag_window().map_err(|e| anyhow!(e.to_string()))?; Ok(json!(true)) }); }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartResizeParams { window_id: String, edge: String }
+
+pub fn op_start_resize(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<StartResizeParams>(params) {
+        Ok(p) => {
+            let Some(dir) = bridge::parse_edge(&p.edge) else {
+                let _ = app.tx_out.send(RpcResponse::error(id, -32602, format!("Invalid resize edge '{}'", p.edge)));
+                return;
+            };
+            if let Some(win) = app.windows.get(&p.window_id) {
+                match win.drag_resize_window(dir) {
+                    Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+                    Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32099, e.to_string())); }
+                }
+            } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ZoomParams { window_id: String, zoom: f64 }
+
+pub fn op_set_zoom(app: &mut App, params: Value, id: RpcId) {
+    match serde_json::from_value::<ZoomParams>(params) {
+        Ok(p) if p.zoom <= 0.0 => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, "zoom must be greater than 0".into())); }
+        Ok(p) => {
+            if let Some(wv) = app.webviews.get(&p.window_id) {
+                match wv.zoom(p.zoom) {
+                    Ok(()) => { let _ = app.tx_out.send(RpcResponse::result(id, json!(true))); }
+                    Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32099, e.to_string())); }
+                }
+            } else { let _ = app.tx_out.send(RpcResponse::error(id, -32001, "Window not found".into())); }
+        }
+        Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct DevtoolsParams { window_id: String }
@@ -532,3 +937,24 @@ pub fn op_open_devtools(app: &mut App, params: Value, id: RpcId) {
         Err(e) => { let _ = app.tx_out.send(RpcResponse::error(id, -32602, e.to_string())); }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#ff8000"), Some((255, 128, 0, 255)));
+        assert_eq!(parse_color("#FF800080"), Some((255, 128, 0, 128)));
+        assert_eq!(parse_color("#f80"), Some((255, 136, 0, 255)));
+        assert_eq!(parse_color("#f808"), Some((255, 136, 0, 136)));
+        assert_eq!(parse_color("#0000"), Some((0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for bad in ["", "#", "ff8000", "#ff", "#ff800", "#ff80000", "#gg0000", "#ff 000", "#ééé", "red"] {
+            assert_eq!(parse_color(bad), None, "{}", bad);
+        }
+    }
+}
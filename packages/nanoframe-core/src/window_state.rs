@@ -0,0 +1,184 @@
+use crate::dialogs::app_path;
+use crate::screen::{monitor_bounds, monitor_work_area, Rect};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tao::event_loop::EventLoopWindowTarget;
+use tao::monitor::MonitorHandle;
+use tao::window::Window;
+
+const STATE_FILE: &str = "window-state.json";
+
+/// Geometry persisted per `windowStateKey`. Bounds are physical pixels (outer position, inner size)
+/// of the last normal (not maximized/fullscreen/minimized) placement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedWindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub fullscreen: bool,
+    pub monitor: Option<String>,
+}
+
+/// A window whose placement is written back to disk on close.
+pub struct TrackedWindow {
+    key: String,
+    file: PathBuf,
+    pub state: Option<SavedWindowState>,
+}
+
+fn read_all(file: &Path) -> HashMap<String, SavedWindowState> {
+    std::fs::read(file).ok().and_then(|b| serde_json::from_slice(&b).ok()).unwrap_or_default()
+}
+
+/// Load the saved state for `key`, adjusted so it lands on a currently connected monitor.
+pub fn load(target: &EventLoopWindowTarget<()>, key: &str, app_name: Option<&str>) -> Result<TrackedWindow> {
+    let file = app_path("appData", app_name).ok_or(anyhow!("appData directory unavailable"))?.join(STATE_FILE);
+    let state = read_all(&file).remove(key).map(|s| clamp_to_monitors(target, s));
+    Ok(TrackedWindow { key: key.to_string(), file, state })
+}
+
+// What placing a window needs to know about a connected monitor
+struct MonitorArea { name: Option<String>, bounds: Rect, work_area: Rect }
+
+fn monitor_area(m: &MonitorHandle) -> MonitorArea {
+    MonitorArea { name: m.name(), bounds: monitor_bounds(m), work_area: monitor_work_area(m) }
+}
+
+fn clamp_to_monitors(target: &EventLoopWindowTarget<()>, s: SavedWindowState) -> SavedWindowState {
+    let monitors: Vec<MonitorArea> = target.available_monitors().map(|m| monitor_area(&m)).collect();
+    let primary = target.primary_monitor().map(|m| monitor_area(&m));
+    clamp(s, &monitors, primary.as_ref())
+}
+
+// The display layout may have changed since the state was saved (monitor unplugged, resolution
+// changed). Keep the window on the monitor it overlaps most, or move it to its old monitor
+// (by name) / the primary one when it would be entirely off-screen.
+fn clamp(mut s: SavedWindowState, monitors: &[MonitorArea], primary: Option<&MonitorArea>) -> SavedWindowState {
+    let rect = Rect { x: s.x, y: s.y, width: s.width, height: s.height };
+    let best = monitors.iter().map(|m| (m, m.bounds.intersection_area(rect))).max_by_key(|(_, a)| *a);
+    let (monitor, visible) = match best {
+        Some((m, a)) if a > 0 => (m, true),
+        _ => {
+            let by_name = monitors.iter().find(|m| m.name.is_some() && m.name == s.monitor);
+            match by_name.or(primary).or_else(|| monitors.first()) {
+                Some(m) => (m, false),
+                None => return s,
+            }
+        }
+    };
+    let area = monitor.work_area;
+    s.width = s.width.min(area.width);
+    s.height = s.height.min(area.height);
+    if visible {
+        s.x = s.x.clamp(area.x, area.x + (area.width - s.width) as i32);
+        s.y = s.y.clamp(area.y, area.y + (area.height - s.height) as i32);
+    } else {
+        s.x = area.x + ((area.width - s.width) / 2) as i32;
+        s.y = area.y + ((area.height - s.height) / 2) as i32;
+    }
+    s.monitor = monitor.name.clone();
+    s
+}
+
+/// Record the window's current placement; called on move/resize and before saving.
+pub fn update(win: &Window, t: &mut TrackedWindow) {
+    let maximized = win.is_maximized();
+    let fullscreen = win.fullscreen().is_some();
+    let monitor = win.current_monitor().and_then(|m| m.name());
+    let normal = !maximized && !fullscreen && !win.is_minimized();
+    // Only a normal window's geometry is worth restoring; otherwise keep the last normal bounds
+    if let (true, Ok(pos)) = (normal, win.outer_position()) {
+        let size = win.inner_size();
+        t.state = Some(SavedWindowState { x: pos.x, y: pos.y, width: size.width, height: size.height, maximized, fullscreen, monitor });
+    } else if let Some(s) = t.state.as_mut() {
+        s.maximized = maximized;
+        s.fullscreen = fullscreen;
+        s.monitor = monitor;
+    }
+}
+
+/// Merge this window's state into the shared state file with an atomic replace.
+pub fn save(t: &TrackedWindow) -> Result<()> {
+    let Some(state) = t.state.clone() else { return Ok(()) };
+    let mut all = read_all(&t.file);
+    all.insert(t.key.clone(), state);
+    if let Some(dir) = t.file.parent() { std::fs::create_dir_all(dir)?; }
+    let tmp = t.file.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(&all)?)?;
+    std::fs::rename(&tmp, &t.file)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, x: i32, y: i32, width: u32, height: u32) -> MonitorArea {
+        let bounds = Rect { x, y, width, height };
+        // A 40px taskbar at the bottom
+        MonitorArea { name: Some(name.into()), bounds, work_area: Rect { height: height - 40, ..bounds } }
+    }
+
+    fn state(x: i32, y: i32, width: u32, height: u32, monitor: Option<&str>) -> SavedWindowState {
+        SavedWindowState { x, y, width, height, maximized: false, fullscreen: false, monitor: monitor.map(Into::into) }
+    }
+
+    #[test]
+    fn keeps_a_visible_window_in_place() {
+        let monitors = [monitor("A", 0, 0, 1920, 1080)];
+        let s = clamp(state(100, 100, 800, 600, None), &monitors, None);
+        assert_eq!((s.x, s.y, s.width, s.height), (100, 100, 800, 600));
+        assert_eq!(s.monitor.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn pulls_a_partly_visible_window_into_the_work_area() {
+        let monitors = [monitor("A", 0, 0, 1920, 1080)];
+        let s = clamp(state(1500, 700, 800, 600, None), &monitors, None);
+        assert_eq!((s.x, s.y), (1120, 440));
+    }
+
+    #[test]
+    fn picks_the_monitor_the_window_overlaps_most() {
+        let monitors = [monitor("A", 0, 0, 1920, 1080), monitor("B", 1920, 0, 1920, 1080)];
+        let s = clamp(state(1800, 100, 800, 600, None), &monitors, None);
+        assert_eq!(s.monitor.as_deref(), Some("B"));
+        assert_eq!((s.x, s.y), (1920, 100));
+    }
+
+    #[test]
+    fn centers_an_off_screen_window_on_its_old_monitor() {
+        let monitors = [monitor("A", 0, 0, 1920, 1080), monitor("B", 1920, 0, 1280, 1024)];
+        let s = clamp(state(5000, 5000, 800, 600, Some("B")), &monitors, Some(&monitors[0]));
+        assert_eq!(s.monitor.as_deref(), Some("B"));
+        assert_eq!((s.x, s.y), (1920 + 240, 192));
+    }
+
+    #[test]
+    fn centers_an_off_screen_window_on_the_primary_monitor() {
+        let monitors = [monitor("A", 0, 0, 1920, 1080), monitor("B", 1920, 0, 1920, 1080)];
+        let s = clamp(state(-5000, 0, 800, 600, Some("gone")), &monitors, Some(&monitors[1]));
+        assert_eq!(s.monitor.as_deref(), Some("B"));
+        assert_eq!((s.x, s.y), (1920 + 560, 220));
+    }
+
+    #[test]
+    fn shrinks_a_window_larger_than_the_work_area() {
+        let monitors = [monitor("A", 0, 0, 1280, 800)];
+        let s = clamp(state(0, 0, 2000, 1500, None), &monitors, None);
+        assert_eq!((s.x, s.y, s.width, s.height), (0, 0, 1280, 760));
+    }
+
+    #[test]
+    fn leaves_the_state_alone_without_monitors() {
+        let s = clamp(state(5000, 5000, 800, 600, Some("A")), &[], None);
+        assert_eq!((s.x, s.y, s.monitor.as_deref()), (5000, 5000, Some("A")));
+    }
+}
@@ -1,3 +1,20 @@
 export { app, AppImpl as App } from "./main/app.js";
+export type {
+  Cookie,
+  CookieStore,
+  DirEntry,
+  DownloadCompleted,
+  DownloadDecision,
+  DownloadStarted,
+  FileDragEvent,
+  FileStat,
+  FsChange,
+  FsRoot,
+  IpcMessage,
+  SecondInstance,
+  SettingsStore,
+  StoreChange,
+  StoreValueType,
+} from "./main/app.js";
 export { BrowserWindow } from "./main/window.js";
-export type { BrowserWindowOptions } from "./main/window.js";
+export type { BrowserWindowOptions, ResizeEdge, Unit, WindowInfo } from "./main/window.js";
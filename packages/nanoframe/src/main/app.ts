@@ -1,12 +1,168 @@
 import { createNanoEvents } from "nanoevents";
-import { RpcClient, withTimeout } from "./core.js";
+import { RpcClient, withTimeout, type Json } from "./core.js";
+
+export type Rect = { x: number; y: number; width: number; height: number };
+export type Display = {
+  name: string | null;
+  bounds: Rect;
+  workArea: Rect;
+  scaleFactor: number;
+  refreshRate: number | null;
+  primary: boolean;
+};
+export type ClipboardSelection = "clipboard" | "primary";
+export type NotificationOptions = {
+  title: string;
+  body?: string;
+  iconPath?: string;
+  urgency?: "low" | "normal" | "critical";
+  timeoutMs?: number;
+  actions?: { id: string; label: string }[];
+  appName?: string;
+};
+export type NotificationEvent = { notificationId: string; actionId?: string };
+export type ClipboardChange = {
+  selection: ClipboardSelection;
+  // Only present when watching with `includeContent: true`
+  text?: string | null;
+  hasImage?: boolean;
+};
+
+export type Cookie = {
+  name: string;
+  value: string;
+  /** Defaults to the host of `url`. */
+  domain?: string;
+  path?: string;
+  secure?: boolean;
+  httpOnly?: boolean;
+  /** Unix time in seconds; omit for a session cookie. */
+  expires?: number;
+  sameSite?: "strict" | "lax" | "none";
+  url?: string;
+};
+/** A window's cookie store, or a profile's through any open window using it. */
+export type CookieStore = { windowId: string } | { profile: string; appName?: string };
+
+export type DownloadStarted = {
+  windowId: string;
+  downloadId: string;
+  url: string;
+  suggestedFilename: string | null;
+  /** Default destination. */
+  path: string;
+  /** True when the window uses `downloadMode: "node"`; answer with `respondToDownload`. */
+  awaitingDecision: boolean;
+};
+export type DownloadCompleted = {
+  windowId: string;
+  downloadId: string;
+  url: string;
+  path: string | null;
+  success: boolean;
+  /** Refused by the download mode, Node or the user. */
+  cancelled: boolean;
+  /** Why an accepted "dialog"/"node" download couldn't be started again from the page. */
+  error?: string;
+};
+/** `accept` (optionally to an absolute `path`), `deny`, or `dialog` to ask the user with a save dialog. */
+export type DownloadDecision =
+  | { action: "accept"; path?: string }
+  | { action: "deny" }
+  | { action: "dialog"; path?: string };
+
+/** Native file drag over a window; `x`/`y` are CSS pixels relative to the page. */
+export type FileDragEvent = {
+  windowId: string;
+  paths?: string[];
+  x?: number;
+  y?: number;
+};
+
+/** Resolve `path` relative to one of the `getPath` directories instead of requiring it absolute. */
+export type FsRoot = {
+  root?: "home" | "temp" | "downloads" | "appData" | "userData";
+  appName?: string;
+};
+export type DirEntry = {
+  name: string;
+  isFile: boolean;
+  isDirectory: boolean;
+  isSymlink: boolean;
+};
+export type FileStat = {
+  size: number;
+  isFile: boolean;
+  isDirectory: boolean;
+  isSymlink: boolean;
+  readonly: boolean;
+  /** Milliseconds since the epoch. */
+  modified: number | null;
+  created: number | null;
+};
+
+/** Debounced batch of changes under an `fs.watch`ed path. */
+export type FsChange = {
+  watchId: string;
+  events: { kind: "create" | "modify" | "rename" | "remove" | "other"; paths: string[] }[];
+};
+
+/** Launch forwarded by a second instance of the app. */
+export type SecondInstance = { argv: string[]; cwd: string | null };
+
+export type StoreChange = { store: string; key: string; value: Json; oldValue: Json };
+export type StoreValueType = "string" | "number" | "boolean" | "object" | "array" | "null" | "any";
+/** A named JSON settings store in `appData/stores/<name>.json`. */
+export interface SettingsStore {
+  /** Values returned for unset keys, and per-key types checked on `set`. */
+  configure(opts: { defaults?: Record<string, Json>; schema?: Record<string, StoreValueType> }): Promise<void>;
+  get<T = Json>(key: string): Promise<T>;
+  set(key: string, value: Json): Promise<void>;
+  /** Resolves to false if the key wasn't set. */
+  delete(key: string): Promise<boolean>;
+  entries(): Promise<Record<string, Json>>;
+  /** Called after any change to this store, from Node or a page. */
+  onChange(cb: (e: StoreChange) => void): () => void;
+}
+
+/** A `window.ipc.postMessage` from a page. `origin`/`url` come from the webview, not the page. */
+export type IpcMessage = {
+  windowId?: string;
+  payload: any;
+  origin?: string;
+  url?: string;
+  /** `true` for the top-level document; `null` where the platform can't tell (macOS, Linux). */
+  isMainFrame?: boolean | null;
+};
 
 export class AppImpl {
   #rpc!: RpcClient;
   #emitter = createNanoEvents<{
     ready: () => void;
     windowAllClosed: () => void;
-    webviewIpc: (e: { windowId?: string; payload: any }) => void;
+    webviewIpc: (e: IpcMessage) => void;
+    webviewIpcBlocked: (e: {
+      windowId: string;
+      origin: string;
+      url: string;
+      isMainFrame: boolean | null;
+    }) => void;
+    permissionDenied: (e: { windowId: string; method: string }) => void;
+    clipboardChanged: (e: ClipboardChange) => void;
+    notificationClicked: (e: NotificationEvent) => void;
+    notificationAction: (e: NotificationEvent) => void;
+    notificationClosed: (e: NotificationEvent) => void;
+    shortcutTriggered: (e: { accelerator: string }) => void;
+    displaysChanged: (e: { displays: Display[] }) => void;
+    downloadStarted: (e: DownloadStarted) => void;
+    downloadCompleted: (e: DownloadCompleted) => void;
+    fileDragEnter: (e: FileDragEvent) => void;
+    fileDragOver: (e: FileDragEvent) => void;
+    fileDrop: (e: FileDragEvent) => void;
+    fileDragLeave: (e: FileDragEvent) => void;
+    fsChanged: (e: FsChange) => void;
+    storeChanged: (e: StoreChange) => void;
+    secondInstance: (e: SecondInstance) => void;
   }>();
   whenReady: Promise<void>;
   #stopKeepAlive?: () => void;
@@ -24,6 +180,40 @@ export class AppImpl {
         this.#emitter.emit("windowAllClosed");
       } else if (method === "webview.ipc") {
         this.#emitter.emit("webviewIpc", params);
+      } else if (method === "webview.ipcBlocked") {
+        this.#emitter.emit("webviewIpcBlocked", params);
+      } else if (method === "webview.permissionDenied") {
+        this.#emitter.emit("permissionDenied", params);
+      } else if (method === "clipboard.changed") {
+        this.#emitter.emit("clipboardChanged", params);
+      } else if (method === "notification.clicked") {
+        this.#emitter.emit("notificationClicked", params);
+      } else if (method === "notification.action") {
+        this.#emitter.emit("notificationAction", params);
+      } else if (method === "notification.closed") {
+        this.#emitter.emit("notificationClosed", params);
+      } else if (method === "shortcut.triggered") {
+        this.#emitter.emit("shortcutTriggered", params);
+      } else if (method === "screen.displaysChanged") {
+        this.#emitter.emit("displaysChanged", params);
+      } else if (method === "download.started") {
+        this.#emitter.emit("downloadStarted", params);
+      } else if (method === "download.completed") {
+        this.#emitter.emit("downloadCompleted", params);
+      } else if (method === "webview.dragEnter") {
+        this.#emitter.emit("fileDragEnter", params);
+      } else if (method === "webview.dragOver") {
+        this.#emitter.emit("fileDragOver", params);
+      } else if (method === "webview.drop") {
+        this.#emitter.emit("fileDrop", params);
+      } else if (method === "webview.dragLeave") {
+        this.#emitter.emit("fileDragLeave", params);
+      } else if (method === "fs.changed") {
+        this.#emitter.emit("fsChanged", params);
+      } else if (method === "store.changed") {
+        this.#emitter.emit("storeChanged", params);
+      } else if (method === "app.secondInstance") {
+        this.#emitter.emit("secondInstance", params);
       }
     });
     await withTimeout(
@@ -35,10 +225,42 @@ export class AppImpl {
   }
 
   on(event: "ready" | "windowAllClosed", cb: () => void): void;
+  on(event: "webviewIpc", cb: (e: IpcMessage) => void): void;
+  on(
+    event: "webviewIpcBlocked",
+    cb: (e: {
+      windowId: string;
+      origin: string;
+      url: string;
+      isMainFrame: boolean | null;
+    }) => void,
+  ): void;
+  on(
+    event: "permissionDenied",
+    cb: (e: { windowId: string; method: string }) => void,
+  ): void;
+  on(event: "clipboardChanged", cb: (e: ClipboardChange) => void): void;
+  on(
+    event: "notificationClicked" | "notificationAction" | "notificationClosed",
+    cb: (e: NotificationEvent) => void,
+  ): void;
+  on(
+    event: "shortcutTriggered",
+    cb: (e: { accelerator: string }) => void,
+  ): void;
+  on(
+    event: "displaysChanged",
+    cb: (e: { displays: Display[] }) => void,
+  ): void;
+  on(event: "downloadStarted", cb: (e: DownloadStarted) => void): void;
+  on(event: "downloadCompleted", cb: (e: DownloadCompleted) => void): void;
   on(
-    event: "webviewIpc",
-    cb: (e: { windowId?: string; payload: any }) => void,
+    event: "fileDragEnter" | "fileDragOver" | "fileDrop" | "fileDragLeave",
+    cb: (e: FileDragEvent) => void,
   ): void;
+  on(event: "fsChanged", cb: (e: FsChange) => void): void;
+  on(event: "storeChanged", cb: (e: StoreChange) => void): void;
+  on(event: "secondInstance", cb: (e: SecondInstance) => void): void;
   on(event: any, cb: any) {
     this.#emitter.on(event, cb);
   }
@@ -80,21 +302,281 @@ export class AppImpl {
   }
 
   async getPath(
-    name: "home" | "temp" | "appData" | "userData",
+    name: "home" | "temp" | "downloads" | "appData" | "userData",
     appName?: string,
   ) {
     await this.whenReady;
     return this.#rpc.call("app.getPath", { name, app_name: appName });
   }
+  /**
+   * Set the app identity used whenever `appName` is omitted (appData paths, stores, secrets,
+   * single-instance lock). Pages always act under this identity.
+   */
+  async setName(name: string) {
+    await this.whenReady;
+    await this.#rpc.call("app.setName", { name });
+  }
 
   async openExternal(target: string) {
     await this.#rpc.call("shell.openExternal", { target });
   }
-  async writeClipboardText(text: string) {
-    await this.#rpc.call("clipboard.writeText", { text });
+  /** Schemes `openExternal` accepts (default `http`, `https`, `mailto`); `file`, `javascript` and `data` need `force`. */
+  async setAllowedSchemes(schemes: string[], opts: { force?: boolean } = {}) {
+    await this.#rpc.call("shell.setAllowedSchemes", { schemes, ...opts });
+  }
+  async openPath(path: string) {
+    await this.#rpc.call("shell.openPath", { path });
+  }
+  async showItemInFolder(path: string) {
+    await this.#rpc.call("shell.showItemInFolder", { path });
+  }
+  async openWith(target: string, app: string) {
+    await this.#rpc.call("shell.openWith", { target, app });
+  }
+  /**
+   * Let the `fs*` methods (and pages with an `fs.*` capability) reach an absolute path or a
+   * `getPath` directory and everything below it. Dialog picks are allowed automatically.
+   */
+  async allowFsPath(target: string | Required<Pick<FsRoot, "root">> & FsRoot): Promise<string> {
+    await this.whenReady;
+    return this.#rpc.call("fs.allow", typeof target === "string" ? { path: target } : target);
+  }
+  async readFile(path: string, opts: FsRoot & { encoding?: "utf8" | "base64" } = {}): Promise<string> {
+    await this.whenReady;
+    const res = await this.#rpc.call("fs.readFile", { ...opts, path });
+    return res.data;
+  }
+  async writeFile(
+    path: string,
+    data: string,
+    opts: FsRoot & { encoding?: "utf8" | "base64"; append?: boolean } = {},
+  ) {
+    await this.whenReady;
+    await this.#rpc.call("fs.writeFile", { ...opts, path, data });
+  }
+  async readDir(path: string, opts: FsRoot = {}): Promise<DirEntry[]> {
+    await this.whenReady;
+    return this.#rpc.call("fs.readDir", { ...opts, path });
+  }
+  async stat(path: string, opts: FsRoot = {}): Promise<FileStat> {
+    await this.whenReady;
+    return this.#rpc.call("fs.stat", { ...opts, path });
+  }
+  async mkdir(path: string, opts: FsRoot & { recursive?: boolean } = {}) {
+    await this.whenReady;
+    await this.#rpc.call("fs.mkdir", { ...opts, path });
+  }
+  async remove(path: string, opts: FsRoot & { recursive?: boolean } = {}) {
+    await this.whenReady;
+    await this.#rpc.call("fs.remove", { ...opts, path });
+  }
+  /**
+   * Watch a path inside the allowed roots; changes arrive as `fsChanged` events. `ignore` globs
+   * work like `.gitignore` lines (`node_modules` matches at any depth). Resolves to the id for `unwatch`.
+   */
+  async watch(
+    path: string,
+    opts: FsRoot & { recursive?: boolean; ignore?: string[]; debounceMs?: number } = {},
+  ): Promise<string> {
+    await this.whenReady;
+    const res = await this.#rpc.call("fs.watch", { ...opts, path });
+    return res.watchId;
+  }
+  async unwatch(watchId: string): Promise<boolean> {
+    await this.whenReady;
+    return this.#rpc.call("fs.unwatch", { watchId });
+  }
+  /** Settings store `name` (default `"settings"`); changes are written to disk atomically. */
+  store(name = "settings", appName?: string): SettingsStore {
+    const base = { store: name, appName };
+    const call = async (method: string, params: Json = {}) => {
+      await this.whenReady;
+      return this.#rpc.call(method, { ...base, ...params });
+    };
+    return {
+      configure: async (opts) => {
+        await call("store.configure", opts);
+      },
+      get: (key) => call("store.get", { key }),
+      set: async (key, value) => {
+        await call("store.set", { key, value });
+      },
+      delete: (key) => call("store.delete", { key }),
+      entries: () => call("store.entries"),
+      onChange: (cb) =>
+        this.#emitter.on("storeChanged", (e) => {
+          if (e.store === name) cb(e);
+        }),
+    };
+  }
+  /** Store a secret in the OS keyring under this app's identity (`appName`). */
+  async setSecret(key: string, value: string, appName?: string) {
+    await this.whenReady;
+    await this.#rpc.call("secrets.set", { key, value, appName });
+  }
+  async getSecret(key: string, appName?: string): Promise<string | null> {
+    await this.whenReady;
+    return this.#rpc.call("secrets.get", { key, appName });
+  }
+  /** Resolves to false if there was no such secret. */
+  async deleteSecret(key: string, appName?: string): Promise<boolean> {
+    await this.whenReady;
+    return this.#rpc.call("secrets.delete", { key, appName });
+  }
+  /** Move a file or folder to the trash / recycle bin. */
+  async trashItem(path: string) {
+    await this.#rpc.call("shell.trashItem", { path });
+  }
+  async beep() {
+    await this.#rpc.call("shell.beep", {});
+  }
+  async writeClipboardText(
+    text: string,
+    selection: ClipboardSelection = "clipboard",
+  ) {
+    await this.#rpc.call("clipboard.writeText", { text, selection });
+  }
+  async readClipboardText(
+    selection: ClipboardSelection = "clipboard",
+  ): Promise<{ text: string }> {
+    return this.#rpc.call("clipboard.readText", { selection });
+  }
+  async writeClipboardHtml(
+    html: string,
+    altText?: string,
+    selection: ClipboardSelection = "clipboard",
+  ) {
+    await this.#rpc.call("clipboard.writeHtml", { html, altText, selection });
+  }
+  async readClipboardHtml(
+    selection: ClipboardSelection = "clipboard",
+  ): Promise<{ html: string }> {
+    return this.#rpc.call("clipboard.readHtml", { selection });
+  }
+  async writeClipboardImage(
+    base64Png: string,
+    selection: ClipboardSelection = "clipboard",
+  ) {
+    await this.#rpc.call("clipboard.writeImage", { base64Png, selection });
+  }
+  async readClipboardImage(
+    selection: ClipboardSelection = "clipboard",
+  ): Promise<{ base64Png: string; width: number; height: number }> {
+    return this.#rpc.call("clipboard.readImage", { selection });
+  }
+  async readClipboardFiles(
+    selection: ClipboardSelection = "clipboard",
+  ): Promise<{ paths: string[] }> {
+    return this.#rpc.call("clipboard.readFiles", { selection });
+  }
+  async clearClipboard(selection: ClipboardSelection = "clipboard") {
+    await this.#rpc.call("clipboard.clear", { selection });
   }
-  async readClipboardText(): Promise<{ text: string }> {
-    return this.#rpc.call("clipboard.readText", {});
+  async watchClipboard(
+    opts: {
+      intervalMs?: number;
+      selection?: ClipboardSelection;
+      includeContent?: boolean;
+    } = {},
+  ) {
+    await this.#rpc.call("clipboard.startWatching", opts);
+  }
+  async unwatchClipboard(): Promise<boolean> {
+    return this.#rpc.call("clipboard.stopWatching", {});
+  }
+  async setClipboardHandoffOnExit(value: boolean) {
+    await this.#rpc.call("clipboard.setHandoffOnExit", { value });
+  }
+
+  async showNotification(
+    opts: NotificationOptions,
+  ): Promise<{ notificationId: string }> {
+    await this.whenReady;
+    return this.#rpc.call("notification.show", opts);
+  }
+
+  // Accelerators look like "CmdOrCtrl+Shift+K"
+  async registerShortcut(accelerator: string) {
+    await this.whenReady;
+    await this.#rpc.call("shortcut.register", { accelerator });
+  }
+  async unregisterShortcut(accelerator: string) {
+    await this.#rpc.call("shortcut.unregister", { accelerator });
+  }
+  async unregisterAllShortcuts(): Promise<number> {
+    return this.#rpc.call("shortcut.unregisterAll", {});
+  }
+
+  async getAllDisplays(): Promise<Display[]> {
+    await this.whenReady;
+    const res = await this.#rpc.call("screen.getAllDisplays", {});
+    return res.displays;
+  }
+  async getPrimaryDisplay(): Promise<Display> {
+    await this.whenReady;
+    return this.#rpc.call("screen.getPrimaryDisplay", {});
+  }
+  async getDisplayNearestPoint(x: number, y: number): Promise<Display> {
+    await this.whenReady;
+    return this.#rpc.call("screen.getDisplayNearestPoint", { x, y });
+  }
+  async getCursorScreenPoint(): Promise<{ x: number; y: number }> {
+    await this.whenReady;
+    return this.#rpc.call("screen.getCursorPosition", {});
+  }
+
+  /**
+   * Answer a `downloadStarted` event for a window with `downloadMode: "node"`. The download is
+   * held (nothing blocks meanwhile) and started again from the page once accepted, which only works
+   * for same-origin and `data:` URLs; unanswered downloads are dropped as cancelled after 5 minutes.
+   */
+  async respondToDownload(downloadId: string, decision: DownloadDecision) {
+    await this.#rpc.call("download.respond", { downloadId, ...decision });
+  }
+
+  /** Delete cookies, storage and cache of a `profile` (see `BrowserWindow` options). */
+  async clearSessionData(profile: string, appName?: string) {
+    await this.whenReady;
+    await this.#rpc.call("session.clearData", { profile, appName });
+  }
+
+  async getCookies(store: CookieStore, url: string, name?: string): Promise<Cookie[]> {
+    await this.whenReady;
+    return this.#rpc.call("cookies.get", { ...store, url, name });
+  }
+  async getAllCookies(store: CookieStore): Promise<Cookie[]> {
+    await this.whenReady;
+    return this.#rpc.call("cookies.getAll", { ...store });
+  }
+  async setCookie(store: CookieStore, cookie: Cookie) {
+    await this.whenReady;
+    await this.#rpc.call("cookies.set", { ...store, cookie });
+  }
+  /** Delete cookies named `name`, optionally limited by url/domain/path; resolves to the count removed. */
+  async deleteCookie(
+    store: CookieStore,
+    name: string,
+    filter: { url?: string; domain?: string; path?: string } = {},
+  ): Promise<number> {
+    await this.whenReady;
+    return this.#rpc.call("cookies.delete", { ...store, name, ...filter });
+  }
+
+  /**
+   * Make this the only running instance of `appName` (default: the name set with `setName`, else
+   * this app's entry script). Resolves to true in the first instance, which then gets
+   * `secondInstance` events; a later instance forwards its argv and cwd there and its core
+   * exits, which ends this process too.
+   */
+  async requestSingleInstanceLock(appName?: string): Promise<boolean> {
+    await this.whenReady;
+    const res = await this.#rpc.call("app.requestSingleInstanceLock", {
+      appName,
+      entry: process.argv[1] ?? process.execPath,
+      argv: process.argv,
+      cwd: process.cwd(),
+    });
+    return res.acquired;
   }
 
   quit() {
@@ -1,4 +1,26 @@
-import { app } from "./app.js";
+import { app, type Cookie } from "./app.js";
+
+/** Logical units are scale-independent (CSS pixels); physical units are device pixels. */
+export type Unit = "logical" | "physical";
+
+type Size = { width: number; height: number };
+type Point = { x: number; y: number };
+type Bounds = {
+  x: number | null;
+  y: number | null;
+  width: number;
+  height: number;
+};
+export type ResizeEdge =
+  | "north"
+  | "south"
+  | "east"
+  | "west"
+  | "northEast"
+  | "northWest"
+  | "southEast"
+  | "southWest";
+type WithUnits<T> = T & { logical: T; physical: T; scaleFactor: number };
 
 export interface BrowserWindowOptions {
   title?: string;
@@ -21,12 +43,80 @@ export interface BrowserWindowOptions {
   maxWidth?: number;
   maxHeight?: number;
   contentSize?: boolean;
+  /** Unit for size/position options, default `"logical"`. */
+  unit?: Unit;
+  /** Remember size, position, maximized/fullscreen state under this key across runs. */
+  windowStateKey?: string;
+  /** App name used to resolve the `appData` directory (see `app.getPath`). */
+  appName?: string;
+  /** Edge-resize zone in CSS pixels; defaults to 5 for resizable windows without decorations. */
+  resizeBorder?: number;
+  /** Transparent window and webview, e.g. for overlays; the page background must be transparent too. */
+  transparent?: boolean;
+  /** `#rgb`, `#rrggbb` or `#rrggbbaa`, shown before the page paints. */
+  backgroundColor?: string;
+  /** Owner window id: the new window stays above it, minimizes and closes with it. */
+  parentWindowId?: string;
+  /** Block input to the parent until this window closes; requires `parentWindowId`. */
+  modal?: boolean;
+  /** Unique name that can be used wherever a window id is accepted. */
+  label?: string;
+  userAgent?: string;
+  /** Initial page zoom, 1 = 100%. */
+  zoom?: number;
+  /** Allow media to play without a user gesture. */
+  autoplay?: boolean;
+  /** Page clipboard access (Linux/Windows; always on for macOS). */
+  clipboard?: boolean;
+  /** Enable devtools; defaults to on in debug builds of the core. */
+  devtools?: boolean;
+  /** Don't persist cookies, storage or cache. */
+  incognito?: boolean;
+  /** macOS: a click on an inactive window also reaches the page. */
+  acceptFirstMouse?: boolean;
+  /** macOS: swipe to go back/forward. */
+  backForwardNavigationGestures?: boolean;
+  /** Windows: zoom with Ctrl+/-/wheel and pinch. */
+  hotkeysZoom?: boolean;
+  /** Isolated session (cookies, storage, cache) stored under `userData/profiles/<name>`. */
+  profile?: string;
+  /** Cookies set before the first navigation (domain defaults to the `url` host). */
+  cookies?: Cookie[];
+  /**
+   * `auto` saves to the downloads folder (default), `dialog` asks the user, `deny` refuses, `node` waits for `app.respondToDownload`.
+   * With `dialog` and `node` the download is re-requested from the page with a plain GET once accepted;
+   * only same-origin and `data:` URLs can be, others complete with `success: false`.
+   */
+  downloadMode?: "auto" | "dialog" | "deny" | "node";
+  /** Absolute directory for downloads instead of the user's downloads folder. */
+  downloadDirectory?: string;
+  /** Handle OS file drops natively and emit `fileDrag*`/`fileDrop` events (default true). */
+  dragDrop?: boolean;
+  /** Also dispatch `nanoframe-dragenter`/`-dragover`/`-drop`/`-dragleave` DOM events with real paths. */
+  dropToPage?: boolean;
+  /** Origins allowed to message Node: exact (`https://app.example.com`), `*`, or `https://*.example.com`. Unset allows any. */
+  ipcAllowedOrigins?: string[];
+  /** Content-Security-Policy header for `html` content (served from a custom protocol instead of `with_html`). */
+  csp?: string;
+  /** Core RPC methods the page may call with `window.nanoframe.invoke` (`clipboard.readText`, `dialog.*`, `*`). */
+  capabilities?: string[];
+}
+
+export interface WindowInfo {
+  windowId: string;
+  label: string | null;
+  title: string;
+  visible: boolean;
+  focused: boolean;
+  url: string | null;
 }
 
 export class BrowserWindow {
   id: string;
-  private constructor(id: string) {
+  label: string | null;
+  private constructor(id: string, label: string | null = null) {
     this.id = id;
+    this.label = label;
   }
 
   static async create(opts: BrowserWindowOptions) {
@@ -37,7 +127,21 @@ export class BrowserWindow {
       delete params.iconPath;
     }
     const res = await app.rpc.call("createWindow", params);
-    return new BrowserWindow(res.windowId);
+    return new BrowserWindow(res.windowId, res.label ?? null);
+  }
+
+  static async list(): Promise<WindowInfo[]> {
+    await app.whenReady;
+    return app.rpc.call("window.list", {});
+  }
+  static async getFocused(): Promise<BrowserWindow | null> {
+    await app.whenReady;
+    const info: WindowInfo | null = await app.rpc.call("window.getFocused", {});
+    return info ? new BrowserWindow(info.windowId, info.label) : null;
+  }
+  static async fromLabel(label: string): Promise<BrowserWindow | null> {
+    const info = (await BrowserWindow.list()).find((w) => w.label === label);
+    return info ? new BrowserWindow(info.windowId, info.label) : null;
   }
 
   async show() {
@@ -61,27 +165,34 @@ export class BrowserWindow {
   async setTitle(title: string) {
     await app.rpc.call("window.setTitle", { windowId: this.id, title });
   }
-  async setSize(width: number, height: number) {
-    await app.rpc.call("window.setSize", { windowId: this.id, width, height });
-  }
-  async getSize(): Promise<{ width: number; height: number }> {
-    return app.rpc.call("window.getSize", { windowId: this.id });
+  async setSize(width: number, height: number, unit: Unit = "logical") {
+    await app.rpc.call("window.setSize", {
+      windowId: this.id,
+      width,
+      height,
+      unit,
+    });
   }
-  async setBounds(bounds: {
-    x?: number;
-    y?: number;
-    width?: number;
-    height?: number;
-  }) {
-    await app.rpc.call("window.setBounds", { windowId: this.id, ...bounds });
+  async getSize(unit: Unit = "logical"): Promise<WithUnits<Size>> {
+    return app.rpc.call("window.getSize", { windowId: this.id, unit });
+  }
+  async setBounds(
+    bounds: {
+      x?: number;
+      y?: number;
+      width?: number;
+      height?: number;
+    },
+    unit: Unit = "logical",
+  ) {
+    await app.rpc.call("window.setBounds", {
+      windowId: this.id,
+      ...bounds,
+      unit,
+    });
   }
-  async getBounds(): Promise<{
-    x?: number;
-    y?: number;
-    width: number;
-    height: number;
-  }> {
-    return app.rpc.call("window.getBounds", { windowId: this.id });
+  async getBounds(unit: Unit = "logical"): Promise<WithUnits<Bounds>> {
+    return app.rpc.call("window.getBounds", { windowId: this.id, unit });
   }
   async center() {
     await app.rpc.call("window.center", { windowId: this.id });
@@ -92,23 +203,33 @@ export class BrowserWindow {
   async setResizable(value: boolean) {
     await app.rpc.call("window.setResizable", { windowId: this.id, value });
   }
-  async setMinSize(width: number, height: number) {
+  async setMinSize(width: number, height: number, unit: Unit = "logical") {
     await app.rpc.call("window.setMinSize", {
       windowId: this.id,
       width,
       height,
+      unit,
     });
   }
-  async setMaxSize(width: number, height: number) {
+  async setMaxSize(width: number, height: number, unit: Unit = "logical") {
     await app.rpc.call("window.setMaxSize", {
       windowId: this.id,
       width,
       height,
+      unit,
     });
   }
   async isVisible(): Promise<boolean> {
     return app.rpc.call("window.isVisible", { windowId: this.id });
   }
+  async getChildren(): Promise<BrowserWindow[]> {
+    const ids: string[] = await app.rpc.call("window.getChildren", { windowId: this.id });
+    return ids.map((id) => new BrowserWindow(id));
+  }
+  async getParent(): Promise<BrowserWindow | null> {
+    const id: string | null = await app.rpc.call("window.getParent", { windowId: this.id });
+    return id ? new BrowserWindow(id) : null;
+  }
   async focus() {
     await app.rpc.call("window.focus", { windowId: this.id });
   }
@@ -139,15 +260,45 @@ export class BrowserWindow {
   async setDecorations(value: boolean) {
     await app.rpc.call("window.setDecorations", { windowId: this.id, value });
   }
-  async setPosition(x: number, y: number) {
-    await app.rpc.call("window.setPosition", { windowId: this.id, x, y });
+  async setPosition(x: number, y: number, unit: Unit = "logical") {
+    await app.rpc.call("window.setPosition", { windowId: this.id, x, y, unit });
+  }
+  async getPosition(unit: Unit = "logical"): Promise<WithUnits<Point>> {
+    return app.rpc.call("window.getPosition", { windowId: this.id, unit });
+  }
+  /** Let mouse events pass through the window to whatever is beneath it. */
+  async setIgnoreCursorEvents(value: boolean) {
+    await app.rpc.call("window.setIgnoreCursorEvents", { windowId: this.id, value });
   }
-  async getPosition(): Promise<{ x: number; y: number }> {
-    return app.rpc.call("window.getPosition", { windowId: this.id });
+  /** Start moving the window with the mouse; call while the left button is held. */
+  async startDrag() {
+    await app.rpc.call("window.startDrag", { windowId: this.id });
+  }
+  /** Start resizing from `edge`; call while the left button is held. */
+  async startResize(edge: ResizeEdge) {
+    await app.rpc.call("window.startResize", { windowId: this.id, edge });
   }
   async postMessage(payload: any) {
     await app.rpc.call("webview.postMessage", { windowId: this.id, payload });
   }
+  async getCookies(url: string, name?: string): Promise<Cookie[]> {
+    return app.getCookies({ windowId: this.id }, url, name);
+  }
+  async setCookie(cookie: Cookie) {
+    await app.setCookie({ windowId: this.id }, cookie);
+  }
+  /** Replace the IPC origin allowlist; `null` allows any origin. */
+  async setIpcAllowedOrigins(origins: string[] | null) {
+    await app.rpc.call("webview.setIpcAllowedOrigins", { windowId: this.id, origins });
+  }
+  /** Toggle native file drop handling and/or delivery of drops to the page. */
+  async setDragDrop(opts: { enabled?: boolean; toPage?: boolean }) {
+    await app.rpc.call("webview.setDragDrop", { windowId: this.id, ...opts });
+  }
+  /** Set the page zoom, 1 = 100%. */
+  async setZoom(zoom: number) {
+    await app.rpc.call("webview.setZoom", { windowId: this.id, zoom });
+  }
   async screenshot(): Promise<{ base64Png: string }> {
     return app.rpc.call("webview.screenshot", { windowId: this.id });
   }